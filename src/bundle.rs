@@ -0,0 +1,531 @@
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use url::Url;
+
+use crate::file::{self, FetchOptions};
+use crate::spec_processor::{
+    detect_spec_version, escape_pointer_segment, pointer_segments, resolve_pointer,
+    splice_ref_target, SpecVersion,
+};
+
+/// Guards against path items that keep referencing each other
+const MAX_INLINE_DEPTH: usize = 32;
+
+/// Pull every `$ref` that points into another file or URL into the specification itself.
+///
+/// Referenced pieces are added as components (or top-level definitions for Swagger 2.0) and the
+/// references are rewritten to point at them. Path items are inlined because they have no
+/// component section to live in. Returns a warning for every reference that could not be resolved.
+pub fn bundle_external_refs(spec: &mut Mapping, source: &str, fetch: &FetchOptions) -> Vec<String> {
+    let mut bundler = Bundler::new(spec, source, fetch.clone());
+    let root_location = bundler.root.clone();
+    let mut root = Value::Mapping(std::mem::take(spec));
+    bundler.rewrite(&mut root, &root_location, &mut Vec::new(), 0);
+    *spec = match root {
+        Value::Mapping(mapping) => mapping,
+        _ => unreachable!("the root of a specification is always a mapping"),
+    };
+    bundler.insert_additions(spec);
+    bundler.warnings
+}
+
+struct Bundler {
+    version: SpecVersion,
+    /// Canonical location of the root specification
+    root: String,
+    /// Loaded external documents, by canonical location
+    documents: HashMap<String, Option<Value>>,
+    /// Internal reference assigned to each external (location, fragment) pair
+    assigned: HashMap<(String, String), String>,
+    /// Component names already in use, per section
+    taken: HashSet<(String, String)>,
+    /// Bundled components to add, in the order they were discovered
+    additions: Vec<(String, String, Value)>,
+    warnings: Vec<String>,
+    fetch: FetchOptions,
+}
+
+impl Bundler {
+    fn new(spec: &Mapping, source: &str, fetch: FetchOptions) -> Self {
+        let version = detect_spec_version(spec);
+        let sections = match version {
+            SpecVersion::Swagger2 => Some(spec),
+            SpecVersion::OpenApi3 => spec
+                .get(Value::String("components".to_string()))
+                .and_then(|v| v.as_mapping()),
+        };
+
+        // Existing component names must never be overwritten by bundled ones
+        let mut taken = HashSet::new();
+        for (section, entries) in sections.into_iter().flatten() {
+            let (Some(section), Some(entries)) = (section.as_str(), entries.as_mapping()) else {
+                continue;
+            };
+            for name in entries.keys().filter_map(|name| name.as_str()) {
+                taken.insert((section.to_string(), name.to_string()));
+            }
+        }
+
+        Self {
+            version,
+            root: canonical_location(source),
+            documents: HashMap::new(),
+            assigned: HashMap::new(),
+            taken,
+            additions: Vec::new(),
+            warnings: Vec::new(),
+            fetch,
+        }
+    }
+
+    /// Rewrite all references in `value`, which lives in the document at `base`.
+    /// `context` holds the keys leading to `value` and is used to pick a component section.
+    fn rewrite(&mut self, value: &mut Value, base: &str, context: &mut Vec<String>, depth: usize) {
+        match value {
+            Value::Mapping(map) => {
+                if let Some(Value::String(reference)) = map.get(Value::String("$ref".to_string())) {
+                    let reference = reference.clone();
+                    if is_path_item_context(context) && is_external(&reference, base, &self.root) {
+                        if depth < MAX_INLINE_DEPTH {
+                            self.inline_path_item(map, &reference, base, context, depth);
+                        }
+                        return;
+                    }
+                    if let Some(internal) = self.internalize(&reference, base, context) {
+                        map.insert(Value::String("$ref".to_string()), Value::String(internal));
+                    }
+                }
+                self.rewrite_discriminator_mapping(map, base, context);
+                for (key, child) in map.iter_mut() {
+                    if key.as_str() == Some("$ref") {
+                        continue;
+                    }
+                    context.push(key.as_str().unwrap_or_default().to_string());
+                    self.rewrite(child, base, context, depth);
+                    context.pop();
+                }
+            }
+            Value::Sequence(seq) => {
+                for item in seq {
+                    context.push("[]".to_string());
+                    self.rewrite(item, base, context, depth);
+                    context.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Discriminator mappings can point at schemas in other files, just like a `$ref`.
+    /// Values without a `/` or `#` are plain schema names and stay as they are.
+    fn rewrite_discriminator_mapping(&mut self, map: &mut Mapping, base: &str, context: &[String]) {
+        let Some(Value::Mapping(targets)) = map
+            .get_mut(Value::String("discriminator".to_string()))
+            .and_then(|discriminator| discriminator.get_mut("mapping"))
+        else {
+            return;
+        };
+        let mut context = context.to_vec();
+        context.extend(["discriminator".to_string(), "mapping".to_string()]);
+        for target in targets.values_mut() {
+            let Value::String(reference) = target else {
+                continue;
+            };
+            if !reference.contains(['/', '#']) {
+                continue;
+            }
+            if let Some(internal) = self.internalize(&reference.clone(), base, &context) {
+                *target = Value::String(internal);
+            }
+        }
+    }
+
+    /// Replace a path item `$ref` with the referenced path item
+    fn inline_path_item(
+        &mut self,
+        map: &mut Mapping,
+        reference: &str,
+        base: &str,
+        context: &mut Vec<String>,
+        depth: usize,
+    ) {
+        let (location, fragment) = split_reference(reference, base);
+        let Some(mut target) = self.resolve(&location, &fragment, reference) else {
+            return;
+        };
+        self.rewrite(&mut target, &location, context, depth + 1);
+        if let Value::Mapping(target_map) = target {
+            *map = splice_ref_target(map, &target_map);
+        }
+    }
+
+    /// Return the internal reference for `reference`, bundling its target when it is external.
+    /// Returns `None` when the reference is already internal to the root document.
+    fn internalize(&mut self, reference: &str, base: &str, context: &[String]) -> Option<String> {
+        let (location, fragment) = split_reference(reference, base);
+        if location == self.root {
+            return if base == self.root {
+                None
+            } else {
+                Some(format!("#{}", fragment))
+            };
+        }
+
+        let key = (location.clone(), fragment.clone());
+        if let Some(internal) = self.assigned.get(&key) {
+            return Some(internal.clone());
+        }
+
+        let mut target = self.resolve(&location, &fragment, reference)?;
+        let section = self.section_for(&fragment, context);
+        let name = self.unique_name(&section, &component_name(&location, &fragment));
+        let internal = match self.version {
+            SpecVersion::Swagger2 => format!("#/{}/{}", section, escape_pointer_segment(&name)),
+            SpecVersion::OpenApi3 => {
+                format!("#/components/{}/{}", section, escape_pointer_segment(&name))
+            }
+        };
+
+        // Register before recursing so that cycles resolve to the same component
+        self.assigned.insert(key, internal.clone());
+        let mut nested_context = match self.version {
+            SpecVersion::Swagger2 => vec![section.clone(), name.clone()],
+            SpecVersion::OpenApi3 => vec!["components".to_string(), section.clone(), name.clone()],
+        };
+        self.rewrite(&mut target, &location, &mut nested_context, 0);
+        self.additions.push((section, name, target));
+        Some(internal)
+    }
+
+    /// Load the document at `location` and resolve `fragment` inside it
+    fn resolve(&mut self, location: &str, fragment: &str, reference: &str) -> Option<Value> {
+        if !self.documents.contains_key(location) {
+            let document = match file::read_spec(location, &self.fetch) {
+                Ok(mapping) => Some(Value::Mapping(mapping)),
+                Err(e) => {
+                    self.warnings
+                        .push(format!("Could not load $ref '{}': {}", reference, e));
+                    None
+                }
+            };
+            self.documents.insert(location.to_string(), document);
+        }
+
+        let document = self.documents.get(location)?.as_ref()?;
+        let target = resolve_pointer(document, fragment);
+        if target.is_none() {
+            self.warnings.push(format!(
+                "Could not resolve $ref '{}': '{}' not found in {}",
+                reference,
+                fragment,
+                file::without_credentials(location)
+            ));
+        }
+        target.cloned()
+    }
+
+    /// Pick the component section for a bundled value
+    fn section_for(&self, fragment: &str, context: &[String]) -> String {
+        let segments: Vec<String> = pointer_segments(fragment);
+
+        // The location inside the external document is the most reliable hint
+        let section = match segments.as_slice() {
+            [components, section, _, ..] if components == "components" => Some(section.as_str()),
+            [section, _, ..] => match section.as_str() {
+                "definitions" => Some("schemas"),
+                "parameters" => Some("parameters"),
+                "responses" => Some("responses"),
+                "securityDefinitions" => Some("securitySchemes"),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        // Otherwise, derive it from where the reference is used
+        let section = section.unwrap_or_else(|| {
+            let tail: Vec<&str> = context.iter().rev().take(2).map(String::as_str).collect();
+            match tail.as_slice() {
+                ["[]", "parameters", ..] => "parameters",
+                [_, "responses", ..] => "responses",
+                ["requestBody", ..] => "requestBodies",
+                [_, "headers", ..] => "headers",
+                [_, "examples", ..] => "examples",
+                [_, "links", ..] => "links",
+                [_, "callbacks", ..] => "callbacks",
+                _ => "schemas",
+            }
+        });
+
+        match self.version {
+            SpecVersion::OpenApi3 => section.to_string(),
+            SpecVersion::Swagger2 => match section {
+                "parameters" => "parameters",
+                "responses" => "responses",
+                "securitySchemes" => "securityDefinitions",
+                _ => "definitions",
+            }
+            .to_string(),
+        }
+    }
+
+    /// Derive a component name that does not collide with an existing one
+    fn unique_name(&mut self, section: &str, wanted: &str) -> String {
+        let mut name = wanted.to_string();
+        let mut counter = 2;
+        while self.taken.contains(&(section.to_string(), name.clone())) {
+            name = format!("{}{}", wanted, counter);
+            counter += 1;
+        }
+        self.taken.insert((section.to_string(), name.clone()));
+        name
+    }
+
+    fn insert_additions(&mut self, spec: &mut Mapping) {
+        for (section, name, value) in self.additions.drain(..) {
+            let sections = match self.version {
+                SpecVersion::Swagger2 => &mut *spec,
+                SpecVersion::OpenApi3 => {
+                    match spec
+                        .entry(Value::String("components".to_string()))
+                        .or_insert_with(|| Value::Mapping(Mapping::new()))
+                    {
+                        Value::Mapping(components) => components,
+                        _ => continue,
+                    }
+                }
+            };
+            if let Value::Mapping(entries) = sections
+                .entry(Value::String(section))
+                .or_insert_with(|| Value::Mapping(Mapping::new()))
+            {
+                entries.insert(Value::String(name), value);
+            }
+        }
+    }
+}
+
+/// Path items and webhooks cannot be components, so references to them are inlined
+fn is_path_item_context(context: &[String]) -> bool {
+    matches!(
+        context
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice(),
+        ["paths" | "webhooks", _]
+    )
+}
+
+fn is_external(reference: &str, base: &str, root: &str) -> bool {
+    split_reference(reference, base).0 != root
+}
+
+/// Split a reference into the canonical location of its document and its fragment
+fn split_reference(reference: &str, base: &str) -> (String, String) {
+    let (document, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    let location = if document.is_empty() {
+        base.to_string()
+    } else {
+        canonical_location(&resolve_location(base, document))
+    };
+    (location, fragment.to_string())
+}
+
+/// Resolve a relative document reference against the document it appears in
+fn resolve_location(base: &str, document: &str) -> String {
+    if Url::parse(document).is_ok_and(|url| url.scheme().len() > 1) {
+        return document.to_string();
+    }
+    if let Ok(base_url) = Url::parse(base) {
+        if base_url.scheme().len() > 1 {
+            return base_url
+                .join(document)
+                .map_or_else(|_| document.to_string(), |url| url.to_string());
+        }
+    }
+    Path::new(base)
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(document)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Make locations comparable, so the same file reached through different paths is loaded once
+fn canonical_location(location: &str) -> String {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return location.to_string();
+    }
+    std::fs::canonicalize(location).map_or_else(
+        |_| location.to_string(),
+        |path| path.to_string_lossy().into_owned(),
+    )
+}
+
+/// Name a bundled component after the last pointer segment, or the file name for whole documents
+fn component_name(location: &str, fragment: &str) -> String {
+    let raw = pointer_segments(fragment)
+        .pop()
+        .filter(|segment| !segment.is_empty())
+        .unwrap_or_else(|| {
+            let file_name = location.rsplit('/').next().unwrap_or(location);
+            Path::new(file_name)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("Bundled")
+                .to_string()
+        });
+    // Component names may only contain letters, digits, '.', '-' and '_'
+    raw.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `files` into a fresh directory and bundle the first one
+    fn bundle(name: &str, files: &[(&str, &str)]) -> (Mapping, Vec<String>) {
+        let dir =
+            std::env::temp_dir().join(format!("apisnip-bundle-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            std::fs::write(dir.join(file), content).unwrap();
+        }
+        let root = dir.join(files[0].0).to_string_lossy().into_owned();
+        let mut spec = file::read_spec(&root, &FetchOptions::default()).unwrap();
+        let warnings = bundle_external_refs(&mut spec, &root, &FetchOptions::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+        (spec, warnings)
+    }
+
+    fn lookup(spec: &Mapping, pointer: &str) -> Value {
+        resolve_pointer(&Value::Mapping(spec.clone()), pointer)
+            .cloned()
+            .unwrap_or_else(|| panic!("{} not found", pointer))
+    }
+
+    const ROOT: &str = r#"
+openapi: 3.0.0
+info: {title: Pets, version: '1'}
+paths:
+  /pets:
+    get:
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema:
+                $ref: './pet.yaml'
+  /owners:
+    $ref: './owners.yaml#/paths/~1owners'
+"#;
+
+    const PET: &str = r#"
+type: object
+discriminator:
+  propertyName: kind
+  mapping:
+    dog: './dog.yaml'
+    cat: Cat
+oneOf:
+  - $ref: './dog.yaml'
+"#;
+
+    const DOG: &str = "type: object\nproperties:\n  bark: {type: boolean}\n";
+
+    const OWNERS: &str = r#"
+paths:
+  /owners:
+    get:
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema:
+                $ref: '#/components/schemas/Owner'
+components:
+  schemas:
+    Owner: {type: object}
+"#;
+
+    #[test]
+    fn external_refs_become_components() {
+        let (spec, warnings) = bundle(
+            "components",
+            &[
+                ("root.yaml", ROOT),
+                ("pet.yaml", PET),
+                ("dog.yaml", DOG),
+                ("owners.yaml", OWNERS),
+            ],
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(
+            lookup(
+                &spec,
+                "#/paths/~1pets/get/responses/200/content/application~1json/schema/$ref"
+            ),
+            "#/components/schemas/pet"
+        );
+        assert_eq!(
+            lookup(&spec, "#/components/schemas/pet/oneOf/0/$ref"),
+            "#/components/schemas/dog"
+        );
+        assert_eq!(lookup(&spec, "#/components/schemas/dog/type"), "object");
+    }
+
+    #[test]
+    fn path_items_are_inlined_with_their_refs_bundled() {
+        let (spec, _) = bundle(
+            "paths",
+            &[
+                ("root.yaml", ROOT),
+                ("pet.yaml", PET),
+                ("dog.yaml", DOG),
+                ("owners.yaml", OWNERS),
+            ],
+        );
+        let schema = "#/paths/~1owners/get/responses/200/content/application~1json/schema/$ref";
+        assert_eq!(lookup(&spec, schema), "#/components/schemas/Owner");
+        assert_eq!(lookup(&spec, "#/components/schemas/Owner/type"), "object");
+    }
+
+    #[test]
+    fn discriminator_mapping_values_are_bundled() {
+        let (spec, _) = bundle(
+            "mapping",
+            &[("root.yaml", ROOT), ("pet.yaml", PET), ("dog.yaml", DOG)],
+        );
+        let mapping = "#/components/schemas/pet/discriminator/mapping";
+        assert_eq!(
+            lookup(&spec, &format!("{}/dog", mapping)),
+            "#/components/schemas/dog"
+        );
+        // Plain schema names are not references
+        assert_eq!(lookup(&spec, &format!("{}/cat", mapping)), "Cat");
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        let (spec, warnings) = bundle("missing", &[("root.yaml", ROOT), ("pet.yaml", PET)]);
+        assert!(warnings
+            .iter()
+            .any(|warning| warning.contains("./dog.yaml")));
+        // The unresolved reference is left for the user to fix
+        assert_eq!(
+            lookup(&spec, "#/components/schemas/pet/oneOf/0/$ref"),
+            "./dog.yaml"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Response details kept next to a cached specification, for revalidating it
+#[derive(Serialize, Deserialize, Default)]
+pub struct CacheMeta {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+}
+
+/// A remote specification as it was last downloaded
+pub struct CachedSpec {
+    pub meta: CacheMeta,
+    pub text: String,
+}
+
+/// Load the cached copy of `url`, if there is one
+pub fn load(url: &str) -> Option<CachedSpec> {
+    let (meta_path, body_path) = entry_paths(url)?;
+    let meta: CacheMeta = serde_json::from_str(&fs::read_to_string(meta_path).ok()?).ok()?;
+    // Guard against the unlikely case of two URLs sharing a hash
+    if meta.url != url {
+        return None;
+    }
+    let text = fs::read_to_string(body_path).ok()?;
+    Some(CachedSpec { meta, text })
+}
+
+/// Store a downloaded specification. The cache is an optimisation, so failures are ignored.
+pub fn store(meta: &CacheMeta, text: &str) {
+    let Some((meta_path, body_path)) = entry_paths(&meta.url) else {
+        return;
+    };
+    let Some(dir) = meta_path.parent() else {
+        return;
+    };
+    let _ = fs::create_dir_all(dir)
+        .and_then(|()| write_private(&body_path, text))
+        .and_then(|()| write_private(&meta_path, &serde_json::to_string_pretty(meta)?));
+}
+
+// Cached specifications may be private, so only the user may read them
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to new files, not to entries written by earlier versions
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+/// The per-user cache directory, following the XDG base directory specification
+fn cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("apisnip"))
+}
+
+fn entry_paths(url: &str) -> Option<(PathBuf, PathBuf)> {
+    let dir = cache_dir()?;
+    let key = format!("{:016x}", fnv1a(url.as_bytes()));
+    Some((
+        dir.join(format!("{}.meta.json", key)),
+        dir.join(format!("{}.body", key)),
+    ))
+}
+
+// A hash that stays the same across Rust releases, unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+
+use crate::spec_processor::pointer_segments;
+
+/// A problem in the specification that was skipped instead of aborting the run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError {
+    /// JSON Pointer to the offending value, like `/paths/~1pets/get`
+    pub pointer: String,
+    pub message: String,
+    /// One-based line and column in the source document, when it could be found
+    pub position: Option<(usize, usize)>,
+}
+
+impl SpecError {
+    pub fn new(pointer: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            pointer: pointer.into(),
+            message: message.into(),
+            position: None,
+        }
+    }
+
+    /// Look up the line and column of the pointer in the source text
+    pub fn locate(mut self, source: &str) -> Self {
+        self.position = locate_pointer(source, &self.pointer);
+        self
+    }
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(
+                f,
+                "{} (line {}, column {}): {}",
+                self.pointer, line, column, self.message
+            ),
+            None => write!(f, "{}: {}", self.pointer, self.message),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+/// Find the position of the key a JSON Pointer ends at, in either YAML or JSON source text.
+///
+/// This is a textual search for each key in turn, so it returns the closest key it could find
+/// when the pointer passes through sequences or keys written in an unusual style.
+fn locate_pointer(source: &str, pointer: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    let mut found = None;
+    for segment in pointer_segments(pointer) {
+        let Some(position) = Some(segment)
+            .filter(|segment| !segment.is_empty())
+            .and_then(|segment| find_key(source, offset, &segment))
+        else {
+            break;
+        };
+        offset = position;
+        found = Some(position);
+    }
+    found.map(|position| line_and_column(source, position))
+}
+
+/// Find the next occurrence of `key` used as a mapping key at or after `offset`
+fn find_key(source: &str, offset: usize, key: &str) -> Option<usize> {
+    [
+        format!("\"{}\"", key),
+        format!("'{}'", key),
+        key.to_string(),
+    ]
+    .iter()
+    .filter_map(|candidate| {
+        source[offset..]
+            .match_indices(candidate.as_str())
+            .map(|(index, _)| offset + index)
+            .find(|&start| is_key_at(source, start, candidate.len()))
+    })
+    .min()
+}
+
+// A key starts a line or follows a delimiter, and is followed by a colon
+fn is_key_at(source: &str, start: usize, len: usize) -> bool {
+    let before = source[..start].chars().next_back();
+    let starts_key = before.is_none_or(|c| c.is_whitespace() || matches!(c, '{' | ',' | '-'));
+    let after = source[start + len..].trim_start_matches([' ', '\t']);
+    starts_key && after.starts_with(':')
+}
+
+fn line_and_column(source: &str, position: usize) -> (usize, usize) {
+    let before = &source[..position];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |newline| &before[newline + 1..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
@@ -0,0 +1,107 @@
+use itertools::Itertools;
+use regex::Regex;
+use url::Url;
+
+use crate::file::{self, FetchOptions, Fetched, Source};
+
+/// Locations where services commonly publish their specification
+const WELL_KNOWN_PATHS: [&str; 5] = [
+    "openapi.json",
+    "openapi.yaml",
+    "swagger.json",
+    "v3/api-docs",
+    "swagger/v1/swagger.json",
+];
+
+/// Patterns for the specification URL in Swagger UI and Redoc pages
+const PAGE_LINK_PATTERNS: [&str; 3] = [
+    // Redoc: <redoc spec-url="...">
+    r#"spec-url\s*=\s*["']([^"']+)["']"#,
+    // Redoc: Redoc.init('...', ...)
+    r#"Redoc\.init\(\s*["']([^"']+)["']"#,
+    // Swagger UI: SwaggerUIBundle({ url: "..." }) and the entries of `urls`
+    r#"["']?\burl["']?\s*:\s*["']([^"']+)["']"#,
+];
+
+/// Find the specification for a URL that points at a service or its documentation page.
+/// Links in the page are tried first, then the well-known locations below the URL and the site root.
+pub fn discover(url: &str, page: Option<&str>, fetch: &FetchOptions) -> Option<Source> {
+    let base = Url::parse(url).ok()?;
+    let mut candidates = page
+        .map(|html| links_in_page(&base, html, fetch))
+        .unwrap_or_default();
+    candidates.extend(well_known_locations(&base));
+    candidates
+        .into_iter()
+        .unique()
+        .filter(|candidate| candidate.as_str() != url)
+        .find_map(|candidate| fetch_specification(candidate.as_str(), fetch))
+}
+
+fn fetch_specification(url: &str, fetch: &FetchOptions) -> Option<Source> {
+    match file::fetch_url(url, fetch).ok()? {
+        Fetched::Document(source) if is_specification(url, &source) => Some(source),
+        _ => None,
+    }
+}
+
+// Services answer unknown paths with all sorts of documents, so only accept actual specifications
+fn is_specification(url: &str, source: &Source) -> bool {
+    file::parse_spec(url, source).is_ok_and(|spec| {
+        ["openapi", "swagger"]
+            .iter()
+            .any(|&key| spec.contains_key(key))
+    })
+}
+
+fn links_in_page(base: &Url, html: &str, fetch: &FetchOptions) -> Vec<Url> {
+    let mut links = links_in_text(base, html);
+
+    // Swagger UI 4 and later keep their configuration in a separate script
+    let script = Regex::new(r#"src\s*=\s*["']([^"']*swagger-initializer\.js)["']"#).unwrap();
+    for script_url in script
+        .captures_iter(html)
+        .filter_map(|captures| base.join(&captures[1]).ok())
+    {
+        if let Ok(Fetched::Document(source)) = file::fetch_url(script_url.as_str(), fetch) {
+            links.extend(links_in_text(&script_url, &source.text));
+        }
+    }
+    links
+}
+
+fn links_in_text(base: &Url, text: &str) -> Vec<Url> {
+    PAGE_LINK_PATTERNS
+        .iter()
+        .map(|pattern| Regex::new(pattern).unwrap())
+        .flat_map(|pattern| {
+            pattern
+                .captures_iter(text)
+                .filter_map(|captures| base.join(&captures[1]).ok())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The well-known paths below the URL itself and below the root of its site
+fn well_known_locations(url: &Url) -> Vec<Url> {
+    let mut directory = url.clone();
+    directory.set_query(None);
+    directory.set_fragment(None);
+    if !directory.path().ends_with('/') {
+        let path = format!("{}/", directory.path());
+        directory.set_path(&path);
+    }
+    let root = directory.join("/").ok();
+
+    [Some(directory), root]
+        .into_iter()
+        .flatten()
+        .unique()
+        .flat_map(|base| {
+            WELL_KNOWN_PATHS
+                .iter()
+                .filter_map(move |path| base.join(path).ok())
+        })
+        .collect()
+}
@@ -5,21 +5,40 @@ use std::time::Duration;
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum Message {
+    AcceptOutputPath,
+    CancelOutputPath,
+    CancelQuit,
+    CancelVisual,
     ClearSearch,
+    ClearSelection,
+    DeselectVisible,
+    EditOutputPath,
     GoToBottom,
     GoToTop,
+    HidePreview,
     HideSearch,
+    InvertSelection,
     KeyPress(KeyEvent),
     Quit,
+    QuitWithoutWriting,
     ScrollDown,
+    ScrollPreview(i32),
     ScrollUp,
+    SelectMethod,
     SelectNext,
     SelectNextPage,
     SelectPrevious,
     SelectPreviousPage,
+    SelectRange(u16),
     SelectRow(u16),
+    SelectVisible,
+    ShowPreview,
     ShowSearch,
+    StartVisual,
     ToggleSelectItemAndSelectNext,
+    ToggleVisualRange,
+    ToggleWarnings,
+    Write,
     WriteAndQuit,
 }
 
@@ -27,7 +46,7 @@ pub fn handle_event(model: &mut AppModel) -> Result<Option<Message>> {
     if event::poll(Duration::from_millis(250))? {
         match event::read()? {
             Event::Key(key) if key.kind == event::KeyEventKind::Press => Ok(handle_key(key, model)),
-            Event::Mouse(mouse) => Ok(handle_mouse(mouse)),
+            Event::Mouse(mouse) => Ok(handle_mouse(mouse, model)),
             _ => Ok(None),
         }
     } else {
@@ -36,13 +55,58 @@ pub fn handle_event(model: &mut AppModel) -> Result<Option<Message>> {
 }
 
 const fn handle_key(key: event::KeyEvent, model: &mut AppModel) -> Option<Message> {
+    if model.path_input.is_some() {
+        return match key.code {
+            KeyCode::Enter => Some(Message::AcceptOutputPath),
+            KeyCode::Esc => Some(Message::CancelOutputPath),
+            _ => Some(Message::KeyPress(key)),
+        };
+    }
+
+    if model.confirm_quit {
+        return match key.code {
+            KeyCode::Char('y') => Some(Message::WriteAndQuit),
+            KeyCode::Char('n') => Some(Message::QuitWithoutWriting),
+            KeyCode::Esc | KeyCode::Char('c') => Some(Message::CancelQuit),
+            _ => None,
+        };
+    }
+
+    if let Some(preview) = &model.preview {
+        let page = preview.page_height as i32;
+        return match key.code {
+            KeyCode::Enter | KeyCode::Char('w') => Some(Message::WriteAndQuit),
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => Some(Message::HidePreview),
+            KeyCode::Char('o') => Some(Message::EditOutputPath),
+            KeyCode::Char('j') | KeyCode::Down => Some(Message::ScrollPreview(1)),
+            KeyCode::Char('k') | KeyCode::Up => Some(Message::ScrollPreview(-1)),
+            KeyCode::PageDown => Some(Message::ScrollPreview(page)),
+            KeyCode::PageUp => Some(Message::ScrollPreview(-page)),
+            KeyCode::Home => Some(Message::ScrollPreview(i32::MIN)),
+            KeyCode::End => Some(Message::ScrollPreview(i32::MAX)),
+            _ => None,
+        };
+    }
+
     if model.search_state.active {
         match key.code {
             KeyCode::Char('u') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
                 Some(Message::ClearSearch)
             }
+            KeyCode::Char('a') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                Some(Message::SelectVisible)
+            }
+            KeyCode::Char('d') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                Some(Message::DeselectVisible)
+            }
+            KeyCode::Char('v') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                Some(Message::StartVisual)
+            }
+            KeyCode::Esc if model.visual_anchor.is_some() => Some(Message::CancelVisual),
             KeyCode::Esc => Some(Message::HideSearch),
-            KeyCode::Char(' ') => Some(Message::ToggleSelectItemAndSelectNext),
+            // Space separates the terms of a query, so selection moves to Tab while searching
+            KeyCode::Tab if model.visual_anchor.is_some() => Some(Message::ToggleVisualRange),
+            KeyCode::Tab => Some(Message::ToggleSelectItemAndSelectNext),
             KeyCode::Up => Some(Message::SelectPrevious),
             KeyCode::Down => Some(Message::SelectNext),
             KeyCode::PageDown => Some(Message::SelectNextPage),
@@ -53,14 +117,25 @@ const fn handle_key(key: event::KeyEvent, model: &mut AppModel) -> Option<Messag
         }
     } else {
         match key.code {
+            KeyCode::Char(' ') if model.visual_anchor.is_some() => Some(Message::ToggleVisualRange),
             KeyCode::Char(' ') => Some(Message::ToggleSelectItemAndSelectNext),
+            KeyCode::Char('v') => Some(Message::StartVisual),
             KeyCode::Char('/') => Some(Message::ShowSearch),
+            KeyCode::Char('a') => Some(Message::SelectVisible),
+            KeyCode::Char('A') | KeyCode::Char('d') => Some(Message::DeselectVisible),
+            KeyCode::Char('i') => Some(Message::InvertSelection),
+            KeyCode::Char('c') => Some(Message::ClearSelection),
+            KeyCode::Char('m') => Some(Message::SelectMethod),
+            KeyCode::Char('!') => Some(Message::ToggleWarnings),
             KeyCode::Char('j') => Some(Message::SelectNext),
             KeyCode::Char('k') => Some(Message::SelectPrevious),
             KeyCode::Char('q') => Some(Message::Quit),
-            KeyCode::Char('w') => Some(Message::WriteAndQuit),
+            KeyCode::Char('s') => Some(Message::Write),
+            KeyCode::Char('S') => Some(Message::EditOutputPath),
+            KeyCode::Char('w') => Some(Message::ShowPreview),
             KeyCode::Up => Some(Message::SelectPrevious),
             KeyCode::Down => Some(Message::SelectNext),
+            KeyCode::Esc if model.visual_anchor.is_some() => Some(Message::CancelVisual),
             KeyCode::Esc => Some(Message::HideSearch),
             KeyCode::PageDown => Some(Message::SelectNextPage),
             KeyCode::PageUp => Some(Message::SelectPreviousPage),
@@ -71,10 +146,24 @@ const fn handle_key(key: event::KeyEvent, model: &mut AppModel) -> Option<Messag
     }
 }
 
-const fn handle_mouse(mouse: event::MouseEvent) -> Option<Message> {
+const fn handle_mouse(mouse: event::MouseEvent, model: &AppModel) -> Option<Message> {
+    if model.path_input.is_some() || model.confirm_quit {
+        return None;
+    }
+    if model.preview.is_some() {
+        return match mouse.kind {
+            MouseEventKind::ScrollDown => Some(Message::ScrollPreview(3)),
+            MouseEventKind::ScrollUp => Some(Message::ScrollPreview(-3)),
+            _ => None,
+        };
+    }
     match mouse.kind {
         MouseEventKind::ScrollDown => Some(Message::ScrollDown),
         MouseEventKind::ScrollUp => Some(Message::ScrollUp),
+        // Terminals that keep shift+click for their own text selection never report it
+        MouseEventKind::Down(_) if mouse.modifiers.contains(event::KeyModifiers::SHIFT) => {
+            Some(Message::SelectRange(mouse.row))
+        }
         MouseEventKind::Down(_) => Some(Message::SelectRow(mouse.row)),
         _ => None,
     }
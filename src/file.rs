@@ -1,84 +1,465 @@
+use clap::Args;
 use color_eyre::eyre::{self, Result};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED,
+};
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
 use serde_yaml::{Mapping, Value};
+use std::fmt;
 use std::fs;
+use std::io::{self, Write};
 use std::path::Path;
+use std::time::Duration;
 use url::Url;
 
+use crate::cache::{self, CacheMeta, CachedSpec};
+use crate::discover;
 use crate::spec_processor::{Endpoint, Status};
 
-pub fn read_spec(path: &str) -> Result<Mapping> {
+/// Stands for stdin when used as the input and stdout when used as the output
+pub const STDIO: &str = "-";
+
+/// The serialization formats a specification can be written in
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// Derive the format from a file extension
+    fn from_extension(path: &str) -> Option<Self> {
+        // Ignore the query string of URLs like `/openapi.json?version=2`
+        let path = path.split(['?', '#']).next().unwrap_or(path);
+        match Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .as_deref()
+        {
+            Some("json") => Some(Self::Json),
+            Some("yaml") | Some("yml") => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Derive the format from an HTTP `Content-Type`, which is often too generic to tell
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_lowercase();
+        if mime.ends_with("json") {
+            Some(Self::Json)
+        } else if mime.ends_with("yaml") || mime.ends_with("yml") {
+            Some(Self::Yaml)
+        } else {
+            None
+        }
+    }
+
+    /// Guess the format from the text itself: JSON documents start with an object
+    fn sniff(text: &str) -> Self {
+        if text
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with('{')
+        {
+            Self::Json
+        } else {
+            Self::Yaml
+        }
+    }
+}
+
+/// The raw text of a specification, with the format its origin claims it has
+pub struct Source {
+    pub text: String,
+    pub format_hint: Option<Format>,
+    /// Where the text was read from, which differs from the input when it was discovered
+    pub location: String,
+}
+
+/// What a URL turned out to serve
+pub enum Fetched {
+    Document(Source),
+    /// An HTML page, like Swagger UI or Redoc, that may link to the specification
+    Page(String),
+}
+
+/// Options for fetching remote specifications and the documents they reference.
+/// Proxies are taken from the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables.
+#[derive(Args, Clone)]
+#[clap(next_help_heading = "Fetching")]
+pub struct FetchOptions {
+    /// Extra HTTP header to send, e.g. 'X-Api-Key: secret' (may be repeated)
+    #[clap(long = "header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Bearer token to send in the Authorization header
+    #[clap(
+        long,
+        value_name = "TOKEN",
+        env = "APISNIP_BEARER_TOKEN",
+        hide_env_values = true
+    )]
+    bearer_token: Option<String>,
+
+    /// Seconds to wait for a response
+    #[clap(long, value_name = "SECONDS", default_value_t = 30)]
+    timeout: u64,
+
+    /// Use the cached copy of remote specifications instead of downloading them
+    #[clap(long)]
+    offline: bool,
+
+    /// The only origin the headers and token are sent to
+    #[clap(skip)]
+    origin: Option<url::Origin>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            headers: Vec::new(),
+            bearer_token: None,
+            timeout: 30,
+            offline: false,
+            origin: None,
+        }
+    }
+}
+
+impl FetchOptions {
+    /// Send the headers and token only to the origin (scheme, host and port) of `input`,
+    /// so that references and links to other hosts never receive them
+    pub fn restrict_credentials_to(&mut self, input: &str) {
+        self.origin = Url::parse(input).ok().map(|url| url.origin());
+    }
+
+    fn client(&self, url: &Url) -> Result<reqwest::blocking::Client> {
+        let builder =
+            reqwest::blocking::Client::builder().timeout(Duration::from_secs(self.timeout));
+        let origin = url.origin();
+        let has_credentials = !self.headers.is_empty() || self.bearer_token.is_some();
+        if !has_credentials || self.origin.as_ref() != Some(&origin) {
+            return Ok(builder.build()?);
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        if let Some(token) = &self.bearer_token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))?;
+            value.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+        // A redirect to another origin must not carry the credentials either
+        let redirect = Policy::custom(move |attempt| {
+            if attempt.url().origin() != origin {
+                attempt.stop()
+            } else if attempt.previous().len() > 10 {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        });
+        Ok(builder
+            .default_headers(headers)
+            .redirect(redirect)
+            .build()?)
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("'{}' is not in the form 'Name: value'", header))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+/// The location with any `user:password@` removed, for anything that is shown or saved
+pub fn without_credentials(location: &str) -> String {
+    match Url::parse(location) {
+        Ok(mut url) if !url.username().is_empty() || url.password().is_some() => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        _ => location.to_string(),
+    }
+}
+
+pub fn read_spec(path: &str, fetch: &FetchOptions) -> Result<Mapping> {
+    parse_spec(path, &read_document(path, fetch)?)
+}
+
+/// Read the raw text of a specification from a file, URL or stdin.
+/// A URL that points at a service or its documentation is searched for the specification.
+pub fn read_source(path: &str, fetch: &FetchOptions) -> Result<Source> {
     let is_url = path.starts_with("http://") || path.starts_with("https://");
-    let input_content = if is_url {
+    if !is_url {
+        return read_document(path, fetch);
+    }
+    match fetch_url(path, fetch) {
+        Ok(Fetched::Document(source)) => Ok(source),
+        Ok(Fetched::Page(html)) => discover::discover(path, Some(&html), fetch).ok_or_else(|| {
+            eyre::eyre!(
+                "Fetching {} returned an HTML page without a link to a specification, \
+                 and none was found at the well-known locations",
+                without_credentials(path)
+            )
+        }),
+        // Only a URL without a document extension that does not exist may be the base URL of a
+        // service. Anything else, like a refused request or an unreachable host, is reported as is.
+        Err(e) if is_not_found(&e) && Format::from_extension(path).is_none() => {
+            discover::discover(path, None, fetch).ok_or_else(|| {
+                e.wrap_err("No specification was found there or at the well-known locations")
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Read a single document from a file, URL or stdin, without looking for it elsewhere
+fn read_document(path: &str, fetch: &FetchOptions) -> Result<Source> {
+    if path == STDIO {
+        return Ok(Source {
+            text: io::read_to_string(io::stdin())?,
+            format_hint: None,
+            location: path.to_string(),
+        });
+    }
+    let is_url = path.starts_with("http://") || path.starts_with("https://");
+    if is_url {
         // Handle URL
-        let url = Url::parse(path)?;
-        let response = reqwest::blocking::get(url)?;
-        response.text()?
+        match fetch_url(path, fetch)? {
+            Fetched::Document(source) => Ok(source),
+            Fetched::Page(_) => Err(eyre::eyre!(
+                "Fetching {} returned an HTML page instead of a specification",
+                without_credentials(path)
+            )),
+        }
     } else {
         // Handle local file
-        fs::read_to_string(path)?
+        Ok(Source {
+            text: fs::read_to_string(path)?,
+            format_hint: Format::from_extension(path),
+            location: path.to_string(),
+        })
+    }
+}
+
+/// A response with an error status
+#[derive(Debug)]
+struct HttpError {
+    url: String,
+    status: StatusCode,
+    content_type: String,
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Fetching {} failed with {} ({})",
+            self.url, self.status, self.content_type
+        )
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Whether the server answered that there is nothing at the URL
+fn is_not_found(error: &eyre::Report) -> bool {
+    error
+        .downcast_ref::<HttpError>()
+        .is_some_and(|error| matches!(error.status, StatusCode::NOT_FOUND | StatusCode::GONE))
+}
+
+/// Download a specification, revalidating the cached copy when there is one
+pub fn fetch_url(path: &str, fetch: &FetchOptions) -> Result<Fetched> {
+    // Credentials in the URL are kept out of the cache and of messages
+    let shown = without_credentials(path);
+    let document = |cached: CachedSpec| {
+        Fetched::Document(Source {
+            location: path.to_string(),
+            ..cached.into()
+        })
     };
 
-    // Detect file extension or content type and parse accordingly
-    let extension = Path::new(path)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.to_lowercase());
-
-    match extension.as_deref() {
-        Some("json") => {
-            let json_value: serde_json::Value = serde_json::from_str(&input_content)?;
-            // Convert JSON to YAML while preserving order
-            let yaml_str = serde_yaml::to_string(&json_value)?;
-            let value: Value = serde_yaml::from_str(&yaml_str)?;
-            if let Value::Mapping(mapping) = value {
-                Ok(mapping)
-            } else {
-                Err(eyre::eyre!("JSON did not convert to a YAML mapping"))
-            }
+    let cached = cache::load(&shown);
+    if fetch.offline {
+        return cached
+            .map(document)
+            .ok_or_else(|| eyre::eyre!("No cached copy of {} to use offline", shown));
+    }
+
+    let url = Url::parse(path)?;
+    let mut request = fetch.client(&url)?.get(url);
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.meta.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.meta.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().map_err(|e| {
+        if e.is_timeout() {
+            eyre::eyre!(
+                "Fetching {} timed out after {} seconds",
+                shown,
+                fetch.timeout
+            )
+        } else {
+            eyre::Report::new(e.without_url()).wrap_err(format!("Fetching {} failed", shown))
         }
-        Some("yaml") | Some("yml") => {
-            let value: Value = serde_yaml::from_str(&input_content)?;
-            if let Value::Mapping(mapping) = value {
-                Ok(mapping)
+    })?;
+    let status = response.status();
+    if status == StatusCode::NOT_MODIFIED {
+        if let Some(cached) = cached {
+            return Ok(document(cached));
+        }
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let meta = CacheMeta {
+        url: shown.clone(),
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+        content_type: header(CONTENT_TYPE),
+    };
+    let content_type = meta.content_type.as_deref().unwrap_or("no content type");
+
+    // Error pages must not be mistaken for a specification
+    if !status.is_success() {
+        return Err(HttpError {
+            url: shown,
+            status,
+            content_type: content_type.to_string(),
+        }
+        .into());
+    }
+    if content_type.starts_with("text/html") {
+        return Ok(Fetched::Page(response.text()?));
+    }
+
+    let text = response.text()?;
+    cache::store(&meta, &text);
+    Ok(document(CachedSpec { meta, text }))
+}
+
+impl From<CachedSpec> for Source {
+    fn from(cached: CachedSpec) -> Self {
+        Self {
+            format_hint: cached
+                .meta
+                .content_type
+                .as_deref()
+                .and_then(Format::from_content_type)
+                .or_else(|| Format::from_extension(&cached.meta.url)),
+            text: cached.text,
+            location: cached.meta.url,
+        }
+    }
+}
+
+/// Parse the text of a specification read from `path`.
+/// The format hint is tried first, then the format the content looks like when that differs.
+pub fn parse_spec(path: &str, source: &Source) -> Result<Mapping> {
+    let sniffed = Format::sniff(&source.text);
+    let format = source.format_hint.unwrap_or(sniffed);
+    parse_as(format, &source.text)
+        .or_else(|error| {
+            if sniffed == format {
+                Err(error)
             } else {
-                Err(eyre::eyre!("YAML did not parse to a mapping"))
+                parse_as(sniffed, &source.text).map_err(|_| error)
             }
+        })
+        .map_err(|error| eyre::eyre!("Could not parse {}: {}", without_credentials(path), error))
+}
+
+fn parse_as(format: Format, text: &str) -> Result<Mapping> {
+    let value = match format {
+        Format::Json => {
+            // Objects keep their key order thanks to serde_json's `preserve_order` feature
+            let json_value: serde_json::Value = serde_json::from_str(text)?;
+            serde_yaml::to_value(json_value)?
         }
-        _ => Err(eyre::eyre!(
-            "Unsupported file format. Please use .json, .yaml, or .yml files"
-        )),
+        Format::Yaml => serde_yaml::from_str(text)?,
+    };
+    match value {
+        Value::Mapping(mapping) => Ok(mapping),
+        _ => Err(eyre::eyre!("the document is not a mapping")),
     }
 }
 
-pub fn write_spec(path: &str, spec: &Mapping) -> Result<()> {
-    let output_content = match Path::new(path)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.to_lowercase())
-        .as_deref()
-    {
-        Some("json") => {
+/// Serialize a specification the way it would be written to `path`.
+/// An explicit `format` wins over the file extension; stdout defaults to YAML.
+pub fn serialize_spec(path: &str, spec: &Mapping, format: Option<Format>) -> Result<String> {
+    let format = format
+        .or_else(|| Format::from_extension(path))
+        .or((path == STDIO).then_some(Format::Yaml));
+    match format {
+        Some(Format::Json) => {
             let json_value = serde_json::to_value(spec)?;
-            serde_json::to_string_pretty(&json_value)?
-        }
-        Some("yaml") | Some("yml") => serde_yaml::to_string(spec)?,
-        _ => {
-            return Err(eyre::eyre!(
-                "Unsupported output format. Please use .json, .yaml, or .yml files"
-            ));
+            Ok(serde_json::to_string_pretty(&json_value)?)
         }
-    };
+        Some(Format::Yaml) => Ok(serde_yaml::to_string(spec)?),
+        None => Err(eyre::eyre!(
+            "Unsupported output format. Please use .json, .yaml, or .yml files, or --format"
+        )),
+    }
+}
 
-    fs::write(path, output_content)?;
+/// Write a specification to a file or stdout
+pub fn write_spec(path: &str, spec: &Mapping, format: Option<Format>) -> Result<()> {
+    let output_content = serialize_spec(path, spec, format)?;
+
+    if path == STDIO {
+        // Leave the shell prompt on a line of its own
+        let mut output_content = output_content;
+        if !output_content.ends_with('\n') {
+            output_content.push('\n');
+        }
+        let mut stdout = io::stdout().lock();
+        let written = stdout
+            .write_all(output_content.as_bytes())
+            .and_then(|()| stdout.flush());
+        // A consumer like `head` may stop reading early, which is not an error
+        if let Err(e) = written {
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+    } else {
+        fs::write(path, output_content)?;
+    }
     Ok(())
 }
 
-pub fn write_spec_to_file(outfile: &str, spec: &Mapping, table_items: &[Endpoint]) -> Result<()> {
+pub fn write_spec_to_file(
+    outfile: &str,
+    spec: &Mapping,
+    table_items: &[Endpoint],
+    format: Option<Format>,
+) -> Result<()> {
     let selected_items: Vec<&Endpoint> = table_items
         .iter()
         .filter(|item| item.status == Status::Selected)
         .collect();
 
     let output = crate::spec_processor::process_spec_for_output(spec, &selected_items)?;
-    write_spec(outfile, &output)
+    write_spec(outfile, &output, format)
 }
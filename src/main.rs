@@ -1,31 +1,55 @@
-use std::io::stdout;
-
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+mod bundle;
+mod cache;
+mod diagnostic;
+mod discover;
 mod event;
 mod file;
+mod query;
+mod recipe;
+mod selection;
 mod spec_processor;
 mod ui;
 
 use crate::ui::color::{rgb_to_indexed, set_color_preferences};
-use crate::ui::{render_detail, render_search, render_table};
+use crate::ui::{
+    render_detail, render_output_path_prompt, render_preview, render_quit_confirmation,
+    render_search, render_table, render_warnings,
+};
 use clap::Parser;
+use color_eyre::eyre::{self, OptionExt};
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::ExecutableCommand;
 use event::{handle_event, Message};
 use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
+use query::Query;
 use ratatui::layout::{Constraint, Layout};
 use ratatui::style::{Color, Style};
 use ratatui::widgets::TableState;
 use ratatui::Frame;
 use serde_yaml::Mapping;
-use spec_processor::{Endpoint, Status};
+use spec_processor::{Endpoint, OperationKey, SectionCount, Status};
 use supports_color::{ColorLevel, Stream};
-use tui_textarea::TextArea;
+use tui_textarea::{CursorMove, TextArea};
 
 #[derive(Default, Clone)]
 pub struct SearchState {
     pub(crate) active: bool,
     pub(crate) text_input: TextArea<'static>,
+    /// The parsed query, kept for highlighting what it matched
+    pub(crate) query: Query,
+}
+
+/// The output as it would be written, shown before writing it
+pub struct PreviewState {
+    pub(crate) lines: Vec<String>,
+    pub(crate) counts: Vec<SectionCount>,
+    pub(crate) scroll: usize,
+    /// Height of the output pane, for paging
+    pub(crate) page_height: u16,
 }
 
 #[derive(Default, PartialEq, Eq)]
@@ -50,6 +74,21 @@ struct AppModel {
     color_mode: Mode,
     default_foreground_color: (u8, u8, u8),
     default_style: Style,
+    warnings: Vec<String>,
+    show_warnings: bool,
+    format: Option<file::Format>,
+    write_on_exit: bool,
+    preview: Option<PreviewState>,
+    /// Input for a different output path, while it is being edited
+    path_input: Option<TextArea<'static>>,
+    /// Whether the selection changed since it was last written
+    dirty: bool,
+    /// Asking whether to write the unsaved selection before quitting
+    confirm_quit: bool,
+    /// Outcome of the last write, shown until the next one
+    status_message: Option<String>,
+    /// The row a visual range starts at, while one is being selected
+    visual_anchor: Option<OperationKey>,
 }
 
 impl Default for AppModel {
@@ -69,6 +108,16 @@ impl Default for AppModel {
             color_mode: Mode::Unspecified,
             default_foreground_color: (0, 0, 0),
             default_style: Style::default(),
+            warnings: Vec::new(),
+            show_warnings: false,
+            format: None,
+            write_on_exit: false,
+            preview: None,
+            path_input: None,
+            dirty: false,
+            confirm_quit: false,
+            status_message: None,
+            visual_anchor: None,
         }
     }
 }
@@ -87,15 +136,35 @@ pub enum Mode {
 #[derive(Parser)]
 #[clap(version, about = about_str())]
 pub struct Args {
-    /// The name of the input file or URL
+    /// The name of the input file or URL, or - for stdin (may be omitted when the recipe names one)
+    #[clap(required_unless_present = "recipe")]
+    input: Option<String>,
+
+    /// The name of the output file, or - for stdout [default: apisnip.out.yaml]
     #[clap()]
-    input: String,
+    outfile: Option<String>,
 
-    /// The name of the output file
-    #[clap(default_value = "apisnip.out.yaml")]
-    outfile: String,
+    /// Pre-select the operations listed in a recipe written by an earlier run
+    #[clap(long, value_name = "FILE")]
+    recipe: Option<String>,
+
+    /// Write the output without opening the TUI
+    #[clap(long)]
+    headless: bool,
+
+    /// Output format, required to write JSON to stdout [default: from the output file extension]
+    #[clap(long, value_enum)]
+    format: Option<file::Format>,
+
+    #[clap(flatten)]
+    selection: selection::SelectionArgs,
+
+    #[clap(flatten)]
+    fetch: file::FetchOptions,
 }
 
+const DEFAULT_OUTFILE: &str = "apisnip.out.yaml";
+
 fn about_str() -> &'static str {
     // Fetch value from the environment variable
     let dynamic_value = env!("GIT_INFO").to_string();
@@ -120,28 +189,108 @@ fn about_str() -> &'static str {
 }
 
 fn main() -> color_eyre::Result<()> {
-    tui::install_panic_hook();
-    let args: Args = Args::parse();
-    stdout().execute(EnableMouseCapture)?;
+    let mut args: Args = Args::parse();
+
+    let recipe = args
+        .recipe
+        .as_deref()
+        .map(recipe::Recipe::load)
+        .transpose()?;
+    let mut infile = args
+        .input
+        .clone()
+        .or_else(|| recipe.as_ref().and_then(|r| r.input.clone()))
+        .ok_or_eyre("No input given and the recipe does not name one")?;
+    let outfile = args
+        .outfile
+        .clone()
+        .or_else(|| recipe.as_ref().and_then(|r| r.output.clone()))
+        .unwrap_or_else(|| DEFAULT_OUTFILE.to_string());
+
+    args.fetch.restrict_credentials_to(&infile);
+    let source = file::read_source(&infile, &args.fetch)?;
+    // Relative references resolve against the specification that was actually found
+    if source.location != infile {
+        eprintln!(
+            "Using the specification found at {}",
+            file::without_credentials(&source.location)
+        );
+        infile = source.location.clone();
+    }
+    let mut spec = file::parse_spec(&infile, &source)?;
+    let mut warnings = bundle::bundle_external_refs(&mut spec, &infile, &args.fetch);
+    let (mut table_items, errors) = spec_processor::fetch_endpoints_from_spec(&spec);
+    warnings.extend(
+        errors
+            .into_iter()
+            .map(|e| e.locate(&source.text).to_string()),
+    );
+    if let Some(recipe) = &recipe {
+        warnings.extend(recipe.apply(&mut table_items));
+    }
 
-    let spec = file::read_spec(&args.input)?;
+    if args.headless || args.selection.is_active() {
+        if recipe.is_none() && !args.selection.has_selectors() {
+            return Err(eyre::eyre!(
+                "--headless and the --exclude options need a --recipe or at least one selection option"
+            ));
+        }
+        for warning in &warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        return run_headless(&args, &infile, &outfile, &spec, table_items);
+    }
+
+    // Continue refining an earlier snip instead of overwriting it
+    if outfile != file::STDIO && Path::new(&outfile).exists() {
+        match file::read_spec(&outfile, &args.fetch) {
+            Ok(previous) => warnings.extend(spec_processor::select_operations_from_output(
+                &previous,
+                &mut table_items,
+            )),
+            Err(e) => warnings.push(format!(
+                "Could not pre-select from existing {}: {}",
+                outfile, e
+            )),
+        }
+    }
+
+    // Keep the TUI out of the specification when it is written to stdout
+    let screen = if outfile == file::STDIO {
+        tui::Screen::Stderr
+    } else {
+        tui::Screen::Stdout
+    };
+    tui::install_panic_hook(screen);
+    screen.writer().execute(EnableMouseCapture)?;
 
     let mut model = AppModel {
-        infile: args.input,
-        outfile: args.outfile,
+        infile,
+        outfile,
         spec,
+        table_items,
+        show_warnings: !warnings.is_empty(),
+        warnings,
+        format: args.format,
         ..Default::default()
     };
 
-    model.color_support = supports_color::on(Stream::Stdout);
-    set_color_preferences(&mut model.color_mode, &mut model.default_foreground_color);
+    model.color_support = supports_color::on(match screen {
+        tui::Screen::Stdout => Stream::Stdout,
+        tui::Screen::Stderr => Stream::Stderr,
+    });
+    set_color_preferences(
+        &mut model.color_mode,
+        &mut model.default_foreground_color,
+        screen == tui::Screen::Stdout,
+    );
     model.default_style = Style::default().fg(Color::Indexed(rgb_to_indexed(
         model.default_foreground_color.0,
         model.default_foreground_color.1,
         model.default_foreground_color.2,
     )));
 
-    model.table_items = spec_processor::fetch_endpoints_from_spec(&model.spec);
+    sort_items_selected_first(&mut model.table_items);
     // Don't preemptively create backup, only when search starts
 
     // Select the first row if no row is selected
@@ -149,7 +298,7 @@ fn main() -> color_eyre::Result<()> {
         model.table_state.select_first();
     }
 
-    let mut terminal = tui::init_terminal()?;
+    let mut terminal = tui::init_terminal(screen)?;
     while model.running_state != RunningState::Done {
         // Render the current view
         terminal.draw(|f| view(&mut model, f))?;
@@ -162,13 +311,47 @@ fn main() -> color_eyre::Result<()> {
             current_msg = update(&mut model, current_msg.unwrap());
         }
     }
-    stdout().execute(DisableMouseCapture)?;
-    tui::restore_terminal()?;
+    screen.writer().execute(DisableMouseCapture)?;
+    tui::restore_terminal(screen)?;
+    for warning in &model.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    // Stdout is written after the terminal is restored, so the output does not end up on the screen
+    if model.write_on_exit {
+        model.write_output()?;
+    }
+    Ok(())
+}
+
+// Select operations from the recipe and command line patterns and write the output without the TUI
+fn run_headless(
+    args: &Args,
+    infile: &str,
+    outfile: &str,
+    spec: &Mapping,
+    mut items: Vec<Endpoint>,
+) -> color_eyre::Result<()> {
+    args.selection.apply(&mut items)?;
+    file::write_spec_to_file(outfile, spec, &items, args.format)?;
+    recipe::save_alongside(infile, outfile, &items)?;
+    let selected = items
+        .iter()
+        .filter(|item| item.status == Status::Selected)
+        .count();
+    let destination = if outfile == file::STDIO {
+        "stdout"
+    } else {
+        outfile
+    };
+    eprintln!("Wrote {} operations to {}", selected, destination);
     Ok(())
 }
 
 fn view(model: &mut AppModel, frame: &mut Frame) {
-    if model.search_state.active {
+    if model.preview.is_some() {
+        render_preview(model, frame.area(), frame);
+    } else if model.search_state.active {
         let [top, search, bottom] = Layout::vertical([
             Constraint::Percentage(80),
             Constraint::Length(2),
@@ -177,22 +360,77 @@ fn view(model: &mut AppModel, frame: &mut Frame) {
         .areas(frame.area());
         render_table(model, top, frame);
         render_search(model, search, frame);
-        render_detail(model, bottom, frame);
+        render_bottom(model, bottom, frame);
     } else {
         let [top, bottom] =
             Layout::vertical([Constraint::Percentage(80), Constraint::Min(10)]).areas(frame.area());
         render_table(model, top, frame);
-        render_detail(model, bottom, frame);
+        render_bottom(model, bottom, frame);
+    }
+
+    // Dialogs are drawn over whatever screen is showing
+    if model.path_input.is_some() {
+        render_output_path_prompt(model, frame.area(), frame);
+    } else if model.confirm_quit {
+        render_quit_confirmation(model, frame.area(), frame);
+    }
+}
+
+// The warnings panel takes the place of the detail pane while it is open
+fn render_bottom(model: &AppModel, area: ratatui::layout::Rect, frame: &mut Frame) {
+    if model.show_warnings && !model.warnings.is_empty() {
+        render_warnings(model, area, frame);
+    } else {
+        render_detail(model, area, frame);
     }
 }
 
 impl AppModel {
+    // Write the selected operations and their recipe, returning how many were written
+    fn write_output(&self) -> color_eyre::Result<usize> {
+        file::write_spec_to_file(&self.outfile, &self.spec, &self.table_items, self.format)?;
+        recipe::save_alongside(&self.infile, &self.outfile, &self.table_items)?;
+        Ok(self
+            .table_items
+            .iter()
+            .filter(|item| item.status == Status::Selected)
+            .count())
+    }
+
+    // Run the current selection through the output processing, exactly as writing would
+    fn build_preview(&self) -> PreviewState {
+        let selected_items: Vec<&Endpoint> = self
+            .table_items
+            .iter()
+            .filter(|item| item.status == Status::Selected)
+            .collect();
+        let (lines, counts) =
+            match spec_processor::process_spec_for_output(&self.spec, &selected_items).and_then(
+                |output| {
+                    let text = file::serialize_spec(&self.outfile, &output, self.format)?;
+                    Ok((text, spec_processor::count_sections(&self.spec, &output)))
+                },
+            ) {
+                Ok((text, counts)) => (text.lines().map(str::to_string).collect(), counts),
+                Err(e) => (
+                    vec![format!("Cannot write {}: {}", self.outfile, e)],
+                    Vec::new(),
+                ),
+            };
+        PreviewState {
+            lines,
+            counts,
+            scroll: 0,
+            page_height: 0,
+        }
+    }
+
     // Helper method to maintain selection when items are reordered
-    fn maintain_selection(&mut self, path_to_follow: &str) {
+    fn maintain_selection(&mut self, key_to_follow: &OperationKey) {
         if let Some(new_idx) = self
             .table_items
             .iter()
-            .position(|item| item.path == path_to_follow)
+            .position(|item| item.has_key(key_to_follow))
         {
             self.table_state.select(Some(new_idx));
         }
@@ -212,8 +450,8 @@ impl AppModel {
     }
 
     // Helper to update item status in both table_items and backup
-    fn toggle_item_status(&mut self, index: usize) -> (String, Status) {
-        let path = self.table_items[index].path.clone();
+    fn toggle_item_status(&mut self, index: usize) -> (OperationKey, Status) {
+        let key = self.table_items[index].key();
         let new_status = if self.table_items[index].status == Status::Selected {
             Status::Unselected
         } else {
@@ -222,25 +460,135 @@ impl AppModel {
 
         // Update in current display
         self.table_items[index].status = new_status;
+        self.dirty = true;
 
         // Update in backup if it exists
         if let Some(backup) = &mut self.table_items_backup {
-            if let Some(pos) = backup.iter().position(|item| item.path == path) {
+            if let Some(pos) = backup.iter().position(|item| item.has_key(&key)) {
                 backup[pos].status = new_status;
             }
         }
 
-        (path, new_status)
+        (key, new_status)
+    }
+
+    // Helper to set the status of every visible item, leaving those hidden by a search alone
+    fn set_visible_status(&mut self, status_of: impl Fn(&Endpoint) -> Status) {
+        let changes = self
+            .table_items
+            .iter()
+            .filter_map(|item| {
+                let status = status_of(item);
+                (item.status != status).then(|| (item.key(), status))
+            })
+            .collect();
+        self.apply_status_changes(&changes);
+    }
+
+    // Helper to unselect every item, including those hidden by a search
+    fn clear_selection(&mut self) {
+        let changes = self
+            .table_items_backup
+            .as_ref()
+            .unwrap_or(&self.table_items)
+            .iter()
+            .filter(|item| item.status == Status::Selected)
+            .map(|item| (item.key(), Status::Unselected))
+            .collect();
+        self.apply_status_changes(&changes);
+    }
+
+    /// The rows between the visual anchor and the current row, when a range is being selected.
+    /// An anchor hidden by a search leaves just the current row.
+    fn visual_range(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.visual_anchor.as_ref()?;
+        let current = self.table_state.selected()?;
+        let start = self
+            .table_items
+            .iter()
+            .position(|item| item.has_key(anchor))
+            .unwrap_or(current);
+        Some(start.min(current)..=start.max(current))
+    }
+
+    // Helper to select the visual range, or deselect it when it is already fully selected
+    fn toggle_visual_range(&mut self) {
+        let Some(range) = self.visual_range() else {
+            return;
+        };
+        self.visual_anchor = None;
+        let Some(rows) = self.table_items.get(range) else {
+            return;
+        };
+        let status = if rows.iter().all(|item| item.status == Status::Selected) {
+            Status::Unselected
+        } else {
+            Status::Selected
+        };
+        let changes = rows
+            .iter()
+            .filter(|item| item.status != status)
+            .map(|item| (item.key(), status))
+            .collect();
+        self.apply_status_changes(&changes);
+    }
+
+    // Helper to find the item index under a screen row of the table, if any
+    fn row_at(&self, row: u16) -> Option<usize> {
+        // Skip if clicked outside the table content area
+        let row_offset = 2; // First row is border, second is header
+        let last_index = self
+            .table_area
+            .map(|area| area.height.saturating_sub(1))
+            .unwrap_or(1);
+
+        if row < row_offset || row > last_index {
+            return None;
+        }
+
+        let row_index = row - row_offset;
+        let scroll_offset = self.table_state.offset();
+        let actual_index = (row_index + scroll_offset as u16) as usize;
+
+        (actual_index < self.table_items.len()).then_some(actual_index)
+    }
+
+    // Helper to update item statuses in both table_items and backup
+    fn apply_status_changes(&mut self, changes: &HashMap<OperationKey, Status>) {
+        if changes.is_empty() {
+            return;
+        }
+        let focused_key = self
+            .table_state
+            .selected()
+            .and_then(|idx| self.table_items.get(idx))
+            .map(Endpoint::key);
+
+        let backup = self.table_items_backup.iter_mut().flatten();
+        for item in self.table_items.iter_mut().chain(backup) {
+            if let Some(&status) = changes.get(&item.key()) {
+                item.status = status;
+            }
+        }
+        self.dirty = true;
+
+        // Reorder items if not in search mode, as toggling a single item does
+        if !self.search_state.active {
+            sort_items_selected_first(&mut self.table_items);
+            if let Some(key) = focused_key {
+                self.maintain_selection(&key);
+            }
+        }
     }
 
     // Filter items based on query and maintain selection
     fn filter_items(&mut self, query: &str) {
         // Remember current selection
-        let selected_path = self
+        let selected_key = self
             .table_state
             .selected()
             .and_then(|idx| self.table_items.get(idx))
-            .map(|item| item.path.clone());
+            .map(Endpoint::key);
 
         // Ensure backup exists
         if self.table_items_backup.is_none() {
@@ -248,28 +596,18 @@ impl AppModel {
         }
 
         let backup = self.table_items_backup.as_ref().unwrap();
+        self.search_state.query = Query::parse(query);
 
         if query.is_empty() {
             // Reset to full list when query is empty
             self.table_items = backup.clone();
             sort_items_selected_first(&mut self.table_items);
         } else {
-            // Filter with weighted scoring
+            // Filter on the query's fields, scoring its free text
+            let query = &self.search_state.query;
             let mut scored_items = backup
                 .iter()
-                .filter_map(|item| {
-                    let path_score = self.matcher.fuzzy_match(&item.path.to_lowercase(), query);
-                    let desc_score = self
-                        .matcher
-                        .fuzzy_match(&item.description.to_lowercase(), query);
-
-                    match (path_score, desc_score) {
-                        (Some(p), Some(d)) => Some((item, p * 2 + d)), // Path counts double
-                        (Some(p), None) => Some((item, p * 2)),
-                        (None, Some(d)) => Some((item, d)),
-                        (None, None) => None,
-                    }
-                })
+                .filter_map(|item| Some((item, query.score(item, &self.matcher)?)))
                 .collect::<Vec<_>>();
 
             // Sort: selected first, then by score
@@ -289,8 +627,8 @@ impl AppModel {
         }
 
         // Try to maintain selection
-        if let Some(path) = selected_path {
-            self.maintain_selection(&path);
+        if let Some(key) = selected_key {
+            self.maintain_selection(&key);
         }
 
         self.ensure_valid_selection();
@@ -300,17 +638,34 @@ impl AppModel {
 fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
     match msg {
         Message::WriteAndQuit => {
-            file::write_spec_to_file(&model.outfile, &model.spec, &model.table_items)
-                .unwrap_or_else(|e| {
-                    eprintln!("Failed to write spec to file: {}", e);
+            // Stdout can only be written once, after the TUI has closed
+            if model.outfile == file::STDIO {
+                model.write_on_exit = true;
+                model.running_state = RunningState::Done;
+                return None;
+            }
+            // Stay open when the write fails, so the selection is not lost
+            match model.write_output() {
+                Ok(_) => {
+                    model.dirty = false;
                     model.running_state = RunningState::Done;
-                });
-            model.running_state = RunningState::Done;
+                }
+                Err(e) => {
+                    model.status_message =
+                        Some(format!("Could not write {}: {}", model.outfile, e));
+                    model.confirm_quit = false;
+                    model.preview = None;
+                }
+            }
             None
         }
 
         Message::Quit => {
-            model.running_state = RunningState::Done;
+            if model.dirty {
+                model.confirm_quit = true;
+            } else {
+                model.running_state = RunningState::Done;
+            }
             None
         }
 
@@ -318,10 +673,10 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
             if model.table_items.is_empty() {
                 return None;
             }
-            
+
             // Natural scrolling: ScrollDown moves the view down (increases offset)
             model.table_state.scroll_down_by(1);
-            
+
             // Keep selection in view: if selection is above visible area, move it down
             if let Some(selected) = model.table_state.selected() {
                 let offset = model.table_state.offset();
@@ -336,16 +691,16 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
             if model.table_items.is_empty() {
                 return None;
             }
-            
+
             // Natural scrolling: ScrollUp moves the view up (decreases offset)
             model.table_state.scroll_up_by(1);
-            
+
             // Keep selection in view: if selection is below visible area, move it up
             if let Some(selected) = model.table_state.selected() {
                 let offset = model.table_state.offset();
                 let visible_rows = calculate_visible_table_rows(model);
                 let last_visible = offset + visible_rows as usize;
-                
+
                 if selected >= last_visible && last_visible > 0 {
                     model.table_state.select(Some(last_visible - 1));
                 }
@@ -406,24 +761,43 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
         }
 
         Message::SelectRow(row) => {
-            // Skip if clicked outside the table content area
-            let row_offset = 2; // First row is border, second is header
-            let last_index = model
-                .table_area
-                .map(|area| area.height.saturating_sub(1))
-                .unwrap_or(1);
-
-            if row < row_offset || row > last_index {
-                return None;
+            if let Some(index) = model.row_at(row) {
+                model.table_state.select(Some(index));
             }
+            None
+        }
 
-            let row_index = row - row_offset;
-            let scroll_offset = model.table_state.offset();
-            let actual_index = (row_index + scroll_offset as u16) as usize;
-
-            if actual_index < model.table_items.len() {
-                model.table_state.select(Some(actual_index));
+        Message::SelectRange(row) => {
+            // The range runs from the current row, or from the anchor of visual mode, to the click
+            let index = model.row_at(row)?;
+            if model.visual_anchor.is_none() {
+                model.visual_anchor = model
+                    .table_state
+                    .selected()
+                    .and_then(|idx| model.table_items.get(idx))
+                    .map(Endpoint::key);
             }
+            model.table_state.select(Some(index));
+            model.toggle_visual_range();
+            None
+        }
+
+        Message::StartVisual => {
+            model.visual_anchor = model
+                .table_state
+                .selected()
+                .and_then(|idx| model.table_items.get(idx))
+                .map(Endpoint::key);
+            None
+        }
+
+        Message::CancelVisual => {
+            model.visual_anchor = None;
+            None
+        }
+
+        Message::ToggleVisualRange => {
+            model.toggle_visual_range();
             None
         }
 
@@ -438,15 +812,15 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
                 return None;
             }
 
-            // Remember next item's path before changes
-            let next_item_path = if current_index < model.table_items.len() - 1 {
-                Some(model.table_items[current_index + 1].path.clone())
+            // Remember next item's key before changes
+            let next_item_key = if current_index < model.table_items.len() - 1 {
+                Some(model.table_items[current_index + 1].key())
             } else {
                 None
             };
 
             // Toggle status of current item
-            let (current_path, _) = model.toggle_item_status(current_index);
+            let (current_key, _) = model.toggle_item_status(current_index);
 
             // Move to next item before reordering
             if current_index < model.table_items.len() - 1 {
@@ -456,17 +830,57 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
             // Reorder items if not in search mode
             if !model.search_state.active {
                 // Use next item for focus if available, otherwise use current
-                let focused_path = next_item_path.unwrap_or(current_path);
+                let focused_key = next_item_key.unwrap_or(current_key);
 
                 // Sort selected items to top
                 sort_items_selected_first(&mut model.table_items);
 
                 // Maintain selection on the focused item
-                model.maintain_selection(&focused_path);
+                model.maintain_selection(&focused_key);
             }
             None
         }
 
+        Message::SelectVisible => {
+            model.set_visible_status(|_| Status::Selected);
+            None
+        }
+
+        Message::DeselectVisible => {
+            model.set_visible_status(|_| Status::Unselected);
+            None
+        }
+
+        Message::InvertSelection => {
+            model.set_visible_status(|item| match item.status {
+                Status::Selected => Status::Unselected,
+                Status::Unselected => Status::Selected,
+            });
+            None
+        }
+
+        Message::ClearSelection => {
+            model.clear_selection();
+            None
+        }
+
+        Message::SelectMethod => {
+            // Select the visible operations sharing the method of the current row
+            let method = model
+                .table_state
+                .selected()
+                .and_then(|idx| model.table_items.get(idx))
+                .map(|item| item.method.method.clone())?;
+            model.set_visible_status(|item| {
+                if item.method.method == method {
+                    Status::Selected
+                } else {
+                    item.status
+                }
+            });
+            None
+        }
+
         Message::SelectNextPage => {
             if !model.table_items.is_empty() {
                 let visible_rows = calculate_visible_table_rows(model);
@@ -483,6 +897,90 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
             None
         }
 
+        Message::ToggleWarnings => {
+            model.show_warnings = !model.show_warnings;
+            None
+        }
+
+        Message::ShowPreview => {
+            model.preview = Some(model.build_preview());
+            None
+        }
+
+        Message::HidePreview => {
+            model.preview = None;
+            None
+        }
+
+        Message::ScrollPreview(delta) => {
+            if let Some(preview) = &mut model.preview {
+                let last = preview
+                    .lines
+                    .len()
+                    .saturating_sub(preview.page_height as usize);
+                preview.scroll = preview
+                    .scroll
+                    .saturating_add_signed(delta as isize)
+                    .min(last);
+            }
+            None
+        }
+
+        Message::EditOutputPath => {
+            let mut input = TextArea::from([model.outfile.clone()]);
+            input.move_cursor(CursorMove::End);
+            model.path_input = Some(input);
+            None
+        }
+
+        Message::AcceptOutputPath => {
+            let outfile = model
+                .path_input
+                .take()
+                .and_then(|input| input.lines().first().map(|line| line.trim().to_string()))
+                .filter(|outfile| !outfile.is_empty())?;
+            model.outfile = outfile;
+            // In the preview, the extension of the new path may change the output format
+            if model.preview.is_some() {
+                model.preview = Some(model.build_preview());
+                None
+            } else {
+                Some(Message::Write)
+            }
+        }
+
+        Message::CancelOutputPath => {
+            model.path_input = None;
+            None
+        }
+
+        Message::Write => {
+            // Stdout can only be written once, after the TUI has closed
+            if model.outfile == file::STDIO {
+                model.status_message =
+                    Some("The output goes to stdout, press w to write it and quit".to_string());
+                return None;
+            }
+            model.status_message = Some(match model.write_output() {
+                Ok(count) => {
+                    model.dirty = false;
+                    format!("Wrote {} operations to {}", count, model.outfile)
+                }
+                Err(e) => format!("Could not write {}: {}", model.outfile, e),
+            });
+            None
+        }
+
+        Message::QuitWithoutWriting => {
+            model.running_state = RunningState::Done;
+            None
+        }
+
+        Message::CancelQuit => {
+            model.confirm_quit = false;
+            None
+        }
+
         Message::ShowSearch => {
             model.search_state.active = true;
             model.search_state.text_input = TextArea::default();
@@ -496,11 +994,11 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
 
         Message::HideSearch => {
             // Remember current selection
-            let selected_path = model
+            let selected_key = model
                 .table_state
                 .selected()
                 .and_then(|idx| model.table_items.get(idx))
-                .map(|item| item.path.clone());
+                .map(Endpoint::key);
 
             model.search_state.active = false;
             model.search_state.text_input = TextArea::default();
@@ -512,8 +1010,8 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
             }
 
             // Try to maintain selection
-            if let Some(path) = selected_path {
-                model.maintain_selection(&path);
+            if let Some(key) = selected_key {
+                model.maintain_selection(&key);
             }
 
             model.ensure_valid_selection();
@@ -521,6 +1019,10 @@ fn update(model: &mut AppModel, msg: Message) -> Option<Message> {
         }
 
         Message::KeyPress(key) => {
+            if let Some(input) = &mut model.path_input {
+                input.input(key);
+                return None;
+            }
             model.search_state.text_input.input(key);
 
             if model.search_state.active {
@@ -550,7 +1052,8 @@ fn sort_items_selected_first(items: &mut [Endpoint]) {
     items.sort_by(|a, b| match (a.status, b.status) {
         (Status::Selected, Status::Unselected) => std::cmp::Ordering::Less,
         (Status::Unselected, Status::Selected) => std::cmp::Ordering::Greater,
-        _ => a.path.cmp(&b.path), // Sort by path when selection status is the same
+        // Sort by location when selection status is the same
+        _ => a.cmp_location(b),
     });
 }
 
@@ -565,7 +1068,7 @@ fn calculate_visible_table_rows(model: &AppModel) -> u16 {
 }
 
 mod tui {
-    use std::io::stdout;
+    use std::io::{stderr, stdout, Write};
     use std::panic;
 
     use ratatui::backend::{Backend, CrosstermBackend};
@@ -575,23 +1078,40 @@ mod tui {
     use ratatui::crossterm::ExecutableCommand;
     use ratatui::Terminal;
 
-    pub fn init_terminal() -> color_eyre::Result<Terminal<impl Backend>> {
+    /// The stream the TUI is drawn on. Keyboard input is read from the terminal either way.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub enum Screen {
+        Stdout,
+        /// Used when the output specification is written to stdout
+        Stderr,
+    }
+
+    impl Screen {
+        pub fn writer(self) -> Box<dyn Write> {
+            match self {
+                Self::Stdout => Box::new(stdout()),
+                Self::Stderr => Box::new(stderr()),
+            }
+        }
+    }
+
+    pub fn init_terminal(screen: Screen) -> color_eyre::Result<Terminal<impl Backend>> {
         enable_raw_mode()?;
-        stdout().execute(EnterAlternateScreen)?;
-        let terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+        screen.writer().execute(EnterAlternateScreen)?;
+        let terminal = Terminal::new(CrosstermBackend::new(screen.writer()))?;
         Ok(terminal)
     }
 
-    pub fn restore_terminal() -> color_eyre::Result<()> {
-        stdout().execute(LeaveAlternateScreen)?;
+    pub fn restore_terminal(screen: Screen) -> color_eyre::Result<()> {
+        screen.writer().execute(LeaveAlternateScreen)?;
         disable_raw_mode()?;
         Ok(())
     }
 
-    pub fn install_panic_hook() {
+    pub fn install_panic_hook(screen: Screen) {
         let original_hook = panic::take_hook();
         panic::set_hook(Box::new(move |panic_info| {
-            stdout().execute(LeaveAlternateScreen).unwrap();
+            screen.writer().execute(LeaveAlternateScreen).unwrap();
             disable_raw_mode().unwrap();
             original_hook(panic_info);
         }));
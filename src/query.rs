@@ -0,0 +1,406 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use regex::Regex;
+
+use crate::selection::glob_to_regex;
+use crate::spec_processor::Endpoint;
+
+// How much a fuzzy match in each field counts towards the score of free text
+const PATH_WEIGHT: i64 = 2;
+const OPERATION_ID_WEIGHT: i64 = 2;
+const SUMMARY_WEIGHT: i64 = 1;
+const TAG_WEIGHT: i64 = 1;
+const PARAMETER_WEIGHT: i64 = 1;
+const SCHEMA_WEIGHT: i64 = 1;
+
+/// A search query like `method:post path:/admin -deprecated "exact phrase" pets`.
+/// Terms separated by spaces must all match; alternatives joined by `|` need only one to match.
+#[derive(Default, Clone)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+/// One or more alternatives, negated by a leading `-`
+#[derive(Clone)]
+struct Term {
+    negated: bool,
+    alternatives: Vec<Filter>,
+}
+
+#[derive(Clone)]
+enum Filter {
+    Method(Regex),
+    Tag(Regex),
+    OperationId(Regex),
+    Schema(Regex),
+    Path(Regex),
+    Deprecated,
+    /// Substring of the path or summary
+    Phrase(String),
+    /// Fuzzy match against the path, operationId, summary, tags, parameters and schemas
+    Text(String),
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Self {
+        let mut terms: Vec<Term> = Vec::new();
+        let mut join_next = false;
+
+        for chunk in split_chunks(query) {
+            // A `|` at either end of a chunk joins it with its neighbour
+            let joins_previous = join_next || chunk.starts_with('|');
+            join_next = chunk.ends_with('|');
+            let chunk = chunk.trim_matches('|');
+            let (negated, chunk) = match chunk.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, chunk),
+            };
+
+            // Values after a `|` within a chunk reuse its field, as in `method:get|post`
+            let mut field = None;
+            let mut alternatives = Vec::new();
+            for part in split_alternatives(chunk) {
+                let filter = match part.split_once(':') {
+                    Some((name, value)) if is_field(name) => {
+                        field = Some(name.to_lowercase());
+                        Filter::new(name, value)
+                    }
+                    _ => match &field {
+                        Some(name) => Filter::new(name, &part),
+                        None => Filter::from_word(&part, negated),
+                    },
+                };
+                alternatives.extend(filter);
+            }
+            if alternatives.is_empty() {
+                continue;
+            }
+
+            match terms.last_mut() {
+                Some(previous) if joins_previous && !negated => {
+                    previous.alternatives.extend(alternatives)
+                }
+                _ => terms.push(Term {
+                    negated,
+                    alternatives,
+                }),
+            }
+        }
+        Self { terms }
+    }
+
+    /// Score an operation against the query, or `None` when it does not match.
+    /// Only free text contributes to the score; filters either match or they don't.
+    pub fn score(&self, item: &Endpoint, matcher: &SkimMatcherV2) -> Option<i64> {
+        let mut total = 0;
+        for term in &self.terms {
+            let score = term
+                .alternatives
+                .iter()
+                .filter_map(|filter| filter.score(item, matcher))
+                .max();
+            match (term.negated, score) {
+                (false, Some(score)) => total += score,
+                (true, None) => {}
+                _ => return None,
+            }
+        }
+        Some(total)
+    }
+
+    /// Positions of the characters in `text` matched by the free text and phrases of the query,
+    /// for showing why an operation matched
+    pub fn highlights(&self, text: &str, matcher: &SkimMatcherV2) -> Vec<usize> {
+        let text = text.to_lowercase();
+        let mut indices: Vec<usize> = self
+            .terms
+            .iter()
+            .filter(|term| !term.negated)
+            .flat_map(|term| &term.alternatives)
+            .flat_map(|filter| match filter {
+                Filter::Text(query) => matcher
+                    .fuzzy_indices(&text, query)
+                    .map(|(_, indices)| indices)
+                    .unwrap_or_default(),
+                Filter::Phrase(phrase) => text
+                    .match_indices(phrase.as_str())
+                    .flat_map(|(start, _)| {
+                        let first = text[..start].chars().count();
+                        first..first + phrase.chars().count()
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+}
+
+impl Filter {
+    /// A `field:value` filter, or nothing while the value is still being typed
+    fn new(field: &str, value: &str) -> Option<Self> {
+        let value = value.trim_matches('"');
+        if value.is_empty() {
+            return None;
+        }
+        let glob = |value: &str| Regex::new(&format!("(?i){}", glob_to_regex(value, false))).ok();
+        Some(match field.to_lowercase().as_str() {
+            "method" => Self::Method(glob(value)?),
+            "tag" => Self::Tag(glob(value)?),
+            "op" => Self::OperationId(glob(value)?),
+            "schema" => Self::Schema(glob(value)?),
+            "path" if value.contains(['*', '?']) => {
+                Self::Path(Regex::new(&format!("(?i){}", glob_to_regex(value, true))).ok()?)
+            }
+            // Without wildcards a path matches itself and everything below it, so `/admin`
+            // matches `/admin/users` but not `/administrators`
+            "path" => {
+                let prefix = regex::escape(value.trim_end_matches('/'));
+                Self::Path(Regex::new(&format!("(?i)^{}(/|$)", prefix)).ok()?)
+            }
+            "is" if value.eq_ignore_ascii_case("deprecated") => Self::Deprecated,
+            _ => return None,
+        })
+    }
+
+    // Negated free text would exclude nearly everything when fuzzy, so it is matched literally
+    fn from_word(word: &str, negated: bool) -> Option<Self> {
+        // A lone quote is a phrase still being typed, which would otherwise match everything
+        if word.trim_matches('"').is_empty() {
+            None
+        } else if word.eq_ignore_ascii_case("deprecated") {
+            Some(Self::Deprecated)
+        } else if word.starts_with('"') || negated {
+            Some(Self::Phrase(word.trim_matches('"').to_lowercase()))
+        } else {
+            Some(Self::Text(word.to_lowercase()))
+        }
+    }
+
+    fn score(&self, item: &Endpoint, matcher: &SkimMatcherV2) -> Option<i64> {
+        let matched = match self {
+            Self::Method(regex) => regex.is_match(&item.method.method),
+            Self::Tag(regex) => item.method.tags.iter().any(|tag| regex.is_match(tag)),
+            Self::OperationId(regex) => item
+                .method
+                .operation_id
+                .as_deref()
+                .is_some_and(|id| regex.is_match(id)),
+            Self::Schema(regex) => item.refs.iter().any(|name| regex.is_match(name)),
+            Self::Path(regex) => regex.is_match(&item.path),
+            Self::Deprecated => item.method.deprecated,
+            Self::Phrase(phrase) => {
+                item.path.to_lowercase().contains(phrase)
+                    || item.summary().to_lowercase().contains(phrase)
+            }
+            Self::Text(text) => {
+                let fuzzy = |value: &str| matcher.fuzzy_match(&value.to_lowercase(), text);
+                let best = |values: &[String]| values.iter().filter_map(|v| fuzzy(v)).max();
+                let scores = [
+                    (PATH_WEIGHT, fuzzy(&item.path)),
+                    (
+                        OPERATION_ID_WEIGHT,
+                        item.method.operation_id.as_deref().and_then(fuzzy),
+                    ),
+                    (SUMMARY_WEIGHT, fuzzy(item.summary())),
+                    (TAG_WEIGHT, best(&item.method.tags)),
+                    (PARAMETER_WEIGHT, best(&item.parameters)),
+                    (SCHEMA_WEIGHT, best(&item.refs)),
+                ];
+                // Fields add up, so an operation matching in several of them ranks higher
+                return scores
+                    .into_iter()
+                    .filter_map(|(weight, score)| Some(weight * score?))
+                    .reduce(|total, score| total + score);
+            }
+        };
+        matched.then_some(0)
+    }
+}
+
+fn is_field(name: &str) -> bool {
+    ["method", "tag", "op", "schema", "path", "is"]
+        .iter()
+        .any(|field| field.eq_ignore_ascii_case(name))
+}
+
+/// Split a query on whitespace, keeping quoted phrases together
+fn split_chunks(query: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                chunk.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !chunk.is_empty() {
+                    chunks.push(std::mem::take(&mut chunk));
+                }
+            }
+            c => chunk.push(c),
+        }
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// Split a chunk on `|`, except within quotes
+fn split_alternatives(chunk: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    for c in chunk.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                parts.last_mut().unwrap().push(c);
+            }
+            '|' if !quoted => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec_processor::Method;
+
+    fn endpoint(method: &str, path: &str, summary: &str, tags: &[&str]) -> Endpoint {
+        Endpoint {
+            method: Method {
+                method: method.to_string(),
+                description: summary.to_string(),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Default::default()
+            },
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn matches(query: &str, item: &Endpoint) -> bool {
+        Query::parse(query)
+            .score(item, &SkimMatcherV2::default())
+            .is_some()
+    }
+
+    #[test]
+    fn spaces_around_a_pipe_join_alternatives() {
+        let a = endpoint("get", "/a", "", &["a"]);
+        let b = endpoint("get", "/b", "", &["b"]);
+        let c = endpoint("get", "/c", "", &["c"]);
+        for query in [
+            "tag:a | tag:b",
+            "tag:a |tag:b",
+            "tag:a| tag:b",
+            "tag:a|tag:b",
+        ] {
+            assert!(matches(query, &a), "{query}");
+            assert!(matches(query, &b), "{query}");
+            assert!(!matches(query, &c), "{query}");
+        }
+    }
+
+    #[test]
+    fn alternatives_inherit_the_field() {
+        assert!(matches("method:get|post", &endpoint("get", "/", "", &[])));
+        assert!(matches("method:get|post", &endpoint("post", "/", "", &[])));
+        assert!(!matches(
+            "method:get|post",
+            &endpoint("delete", "/", "", &[])
+        ));
+    }
+
+    #[test]
+    fn terms_must_all_match() {
+        let query = "method:post path:/admin";
+        assert!(matches(query, &endpoint("post", "/admin/users", "", &[])));
+        assert!(!matches(query, &endpoint("get", "/admin/users", "", &[])));
+        assert!(!matches(query, &endpoint("post", "/users", "", &[])));
+    }
+
+    #[test]
+    fn negated_phrase_excludes_literal_matches() {
+        let query = r#"-"all pets""#;
+        assert!(!matches(
+            query,
+            &endpoint("get", "/pets", "List all pets", &[])
+        ));
+        assert!(matches(query, &endpoint("get", "/pets", "List pets", &[])));
+        assert!(matches(
+            query,
+            &endpoint("get", "/pets", "All the pets", &[])
+        ));
+    }
+
+    #[test]
+    fn negated_deprecated() {
+        let mut item = endpoint("get", "/old", "", &[]);
+        assert!(matches("-deprecated", &item));
+        item.method.deprecated = true;
+        assert!(!matches("-deprecated", &item));
+        assert!(matches("is:deprecated", &item));
+    }
+
+    #[test]
+    fn unbalanced_quotes_while_typing() {
+        let item = endpoint("get", "/pets", "List pets", &["pets"]);
+        assert!(matches(r#""list pe"#, &item));
+        assert!(matches(r#"tag:"pets"#, &item));
+        assert!(matches(r#"-""#, &item));
+        assert!(matches(r#"""#, &item));
+        assert_eq!(split_chunks(r#"a "b c"#), vec!["a", r#""b c"#]);
+    }
+
+    #[test]
+    fn quotes_keep_spaces_and_pipes() {
+        assert_eq!(
+            split_chunks(r#"method:get "a b|c" d"#),
+            vec!["method:get", r#""a b|c""#, "d"]
+        );
+        assert_eq!(split_alternatives(r#""a|b"|c"#), vec![r#""a|b""#, "c"]);
+    }
+
+    #[test]
+    fn path_prefix_stops_at_a_segment_boundary() {
+        for query in ["path:/admin", "path:/admin/"] {
+            assert!(matches(query, &endpoint("get", "/admin", "", &[])));
+            assert!(matches(query, &endpoint("get", "/admin/users", "", &[])));
+            assert!(!matches(
+                query,
+                &endpoint("get", "/administrators", "", &[])
+            ));
+            assert!(!matches(query, &endpoint("get", "/admin-legacy", "", &[])));
+        }
+        assert!(matches("path:/", &endpoint("get", "/users", "", &[])));
+    }
+
+    #[test]
+    fn path_wildcards_stay_within_a_segment() {
+        assert!(matches(
+            "path:/users/*/orders",
+            &endpoint("get", "/users/{id}/orders", "", &[])
+        ));
+        assert!(!matches(
+            "path:/users/*",
+            &endpoint("get", "/users/{id}/orders", "", &[])
+        ));
+    }
+
+    #[test]
+    fn unknown_fields_are_free_text() {
+        assert!(matches(
+            "foo:bar",
+            &endpoint("get", "/x", "See foo:bar", &[])
+        ));
+        assert!(!matches("foo:bar", &endpoint("get", "/x", "See foo", &[])));
+    }
+}
@@ -0,0 +1,217 @@
+use color_eyre::eyre::{self, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+use crate::file;
+use crate::spec_processor::{Endpoint, EndpointKind, Status};
+
+/// A reusable record of which operations were snipped from which specification
+#[derive(Serialize, Deserialize, Default)]
+pub struct Recipe {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default)]
+    pub operations: Vec<RecipeEntry>,
+}
+
+/// A selected operation, identified by operationId and/or method and path (or webhook name)
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+}
+
+impl RecipeEntry {
+    fn matches_operation_id(&self, item: &Endpoint) -> bool {
+        self.operation_id
+            .as_ref()
+            .is_some_and(|operation_id| item.method.operation_id.as_ref() == Some(operation_id))
+    }
+
+    fn matches_location(&self, item: &Endpoint) -> bool {
+        let (kind, path) = match (&self.path, &self.webhook) {
+            (Some(path), _) => (EndpointKind::Path, path),
+            (None, Some(webhook)) => (EndpointKind::Webhook, webhook),
+            (None, None) => return false,
+        };
+        self.method.as_ref().is_some_and(|method| {
+            item.kind == kind
+                && item.path == *path
+                && item.method.method.eq_ignore_ascii_case(method)
+        })
+    }
+
+    fn describe(&self) -> String {
+        let method = self.method.as_deref().unwrap_or_default().to_uppercase();
+        let location = match (&self.path, &self.webhook) {
+            (Some(path), _) => format!("{} {}", method, path),
+            (None, Some(webhook)) => format!("{} webhook {}", method, webhook),
+            (None, None) => String::new(),
+        }
+        .trim()
+        .to_string();
+        match (&self.operation_id, location.is_empty()) {
+            (Some(operation_id), true) => format!("operationId '{}'", operation_id),
+            (Some(operation_id), false) => format!("{} (operationId '{}')", location, operation_id),
+            (None, _) => location,
+        }
+    }
+}
+
+impl Recipe {
+    /// Build a recipe from the currently selected operations.
+    /// Recipes tend to get committed, so credentials in the input URL are left out.
+    pub fn from_selection(input: &str, output: &str, items: &[Endpoint]) -> Self {
+        Self {
+            input: Some(file::without_credentials(input)),
+            output: Some(output.to_string()),
+            operations: items
+                .iter()
+                .filter(|item| item.status == Status::Selected)
+                .map(|item| RecipeEntry {
+                    method: Some(item.method.method.clone()),
+                    path: (item.kind == EndpointKind::Path).then(|| item.path.clone()),
+                    webhook: (item.kind == EndpointKind::Webhook).then(|| item.path.clone()),
+                    operation_id: item.method.operation_id.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Load a recipe, resolving its relative input and output against the recipe's own location
+    pub fn load(path: &str) -> Result<Self> {
+        let mapping = file::read_spec(path, &file::FetchOptions::default())?;
+        let mut recipe: Self = serde_yaml::from_value(Value::Mapping(mapping))
+            .map_err(|e| eyre::eyre!("Invalid recipe file {}: {}", path, e))?;
+        recipe.input = recipe.input.map(|input| resolve_location(path, input));
+        recipe.output = recipe.output.map(|output| resolve_location(path, output));
+        Ok(recipe)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        match serde_yaml::to_value(self)? {
+            Value::Mapping(mapping) => file::write_spec(path, &mapping, Some(file::Format::Yaml)),
+            _ => Err(eyre::eyre!("Recipe did not serialize to a mapping")),
+        }
+    }
+
+    /// Mark every operation listed in the recipe as selected.
+    /// Returns a description of each entry that no longer exists in the specification.
+    pub fn apply(&self, items: &mut [Endpoint]) -> Vec<String> {
+        let mut missing = Vec::new();
+        for entry in &self.operations {
+            // The operationId survives path renames, so prefer it and only fall back to
+            // method and path when no operation carries it any more
+            let by_operation_id = items.iter().any(|item| entry.matches_operation_id(item));
+            let mut found = false;
+            for item in items.iter_mut().filter(|item| {
+                if by_operation_id {
+                    entry.matches_operation_id(item)
+                } else {
+                    entry.matches_location(item)
+                }
+            }) {
+                item.status = Status::Selected;
+                found = true;
+            }
+            if !found {
+                missing.push(format!(
+                    "Recipe entry {} no longer exists in the specification",
+                    entry.describe()
+                ));
+            }
+        }
+        missing
+    }
+}
+
+/// The recipe is written as YAML next to the output, e.g. `apisnip.out.json` gets `apisnip.out.recipe.yaml`
+pub fn recipe_path_for(outfile: &str) -> String {
+    let path = Path::new(outfile);
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("apisnip");
+    path.with_file_name(format!("{}.recipe.yaml", stem))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Write the recipe for the current selection alongside the output file.
+/// There is nothing to write it alongside when the output goes to stdout.
+pub fn save_alongside(infile: &str, outfile: &str, items: &[Endpoint]) -> Result<()> {
+    if outfile == file::STDIO {
+        return Ok(());
+    }
+    let recipe_path = recipe_path_for(outfile);
+    // Local paths are stored relative to the recipe, so it keeps working from any directory
+    let directory = Path::new(&recipe_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    Recipe::from_selection(
+        &relative_location(infile, directory),
+        &relative_location(outfile, directory),
+        items,
+    )
+    .save(&recipe_path)
+}
+
+fn is_local(location: &str) -> bool {
+    location != file::STDIO && !location.starts_with("http://") && !location.starts_with("https://")
+}
+
+/// Resolve a location read from a recipe against the location of the recipe itself
+fn resolve_location(recipe_path: &str, location: String) -> String {
+    if !is_local(&location) || Path::new(&location).is_absolute() {
+        return location;
+    }
+    if !is_local(recipe_path) {
+        return Url::parse(recipe_path)
+            .and_then(|base| base.join(&location))
+            .map_or(location, |url| url.to_string());
+    }
+    Path::new(recipe_path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(&location)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Express a local path relative to `directory`, leaving URLs and stdin alone
+fn relative_location(location: &str, directory: &Path) -> String {
+    if !is_local(location) {
+        return location.to_string();
+    }
+    let (Ok(target), Ok(base)) = (
+        std::path::absolute(location),
+        std::path::absolute(directory),
+    ) else {
+        return location.to_string();
+    };
+    let target: Vec<Component> = target.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = target.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    // Paths on different drives have nothing in common to be relative to
+    if common == 0 {
+        return location.to_string();
+    }
+    let mut relative: PathBuf = base[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(&target[common..]);
+    relative.to_string_lossy().into_owned()
+}
@@ -0,0 +1,285 @@
+use clap::Args;
+use color_eyre::eyre::{self, Result};
+use regex::Regex;
+
+use crate::spec_processor::{Endpoint, EndpointKind, Status, HTTP_METHODS};
+
+/// Options for selecting operations without the interactive interface
+#[derive(Args, Default)]
+#[clap(next_help_heading = "Selection (runs without the TUI)")]
+pub struct SelectionArgs {
+    /// Select operations by path glob, optionally prefixed by a method, e.g. 'GET /users/**'
+    #[clap(long = "select", value_name = "PATTERN")]
+    select: Vec<String>,
+
+    /// Select operations by path regex, optionally prefixed by a method, e.g. 'POST ^/admin/'
+    #[clap(long = "select-regex", value_name = "PATTERN")]
+    select_regex: Vec<String>,
+
+    /// Select webhook operations by name glob, optionally prefixed by a method
+    #[clap(long = "select-webhook", value_name = "PATTERN")]
+    select_webhook: Vec<String>,
+
+    /// Select operations carrying a tag (glob)
+    #[clap(long = "select-tag", value_name = "TAG")]
+    select_tag: Vec<String>,
+
+    /// Select operations by operationId (glob)
+    #[clap(long = "select-operation", value_name = "OPERATION_ID")]
+    select_operation: Vec<String>,
+
+    /// Exclude operations by path glob, optionally prefixed by a method
+    #[clap(long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// Exclude operations with this HTTP method
+    #[clap(long = "exclude-method", value_name = "METHOD")]
+    exclude_method: Vec<String>,
+
+    /// Exclude operations carrying a tag (glob)
+    #[clap(long = "exclude-tag", value_name = "TAG")]
+    exclude_tag: Vec<String>,
+}
+
+/// What part of an operation a pattern is matched against
+enum Target {
+    Path(Option<String>),
+    Webhook(Option<String>),
+    Tag,
+    OperationId,
+    Method,
+}
+
+/// A single compiled selection or exclusion pattern
+struct Pattern {
+    /// The pattern as given on the command line, used for reporting
+    source: String,
+    target: Target,
+    regex: Regex,
+}
+
+impl Pattern {
+    fn path_glob(flag: &str, pattern: &str) -> Result<Self> {
+        let (method, path) = split_method(pattern);
+        Self::new(
+            flag,
+            pattern,
+            Target::Path(method),
+            &glob_to_regex(path, true),
+        )
+    }
+
+    fn webhook_glob(flag: &str, pattern: &str) -> Result<Self> {
+        let (method, name) = split_method(pattern);
+        Self::new(
+            flag,
+            pattern,
+            Target::Webhook(method),
+            &glob_to_regex(name, false),
+        )
+    }
+
+    fn path_regex(flag: &str, pattern: &str) -> Result<Self> {
+        let (method, path) = split_method(pattern);
+        Self::new(flag, pattern, Target::Path(method), path)
+    }
+
+    fn glob(flag: &str, pattern: &str, target: Target) -> Result<Self> {
+        Self::new(flag, pattern, target, &glob_to_regex(pattern, false))
+    }
+
+    fn new(flag: &str, pattern: &str, target: Target, regex: &str) -> Result<Self> {
+        Ok(Self {
+            source: format!("{} '{}'", flag, pattern),
+            target,
+            regex: Regex::new(regex)
+                .map_err(|e| eyre::eyre!("Invalid pattern for {} '{}': {}", flag, pattern, e))?,
+        })
+    }
+
+    fn matches(&self, item: &Endpoint) -> bool {
+        match &self.target {
+            Target::Path(method) => {
+                item.kind == EndpointKind::Path
+                    && method
+                        .as_ref()
+                        .is_none_or(|m| m.eq_ignore_ascii_case(&item.method.method))
+                    && self.regex.is_match(&item.path)
+            }
+            Target::Webhook(method) => {
+                item.kind == EndpointKind::Webhook
+                    && method
+                        .as_ref()
+                        .is_none_or(|m| m.eq_ignore_ascii_case(&item.method.method))
+                    && self.regex.is_match(&item.path)
+            }
+            Target::Tag => item.method.tags.iter().any(|tag| self.regex.is_match(tag)),
+            Target::OperationId => item
+                .method
+                .operation_id
+                .as_deref()
+                .is_some_and(|id| self.regex.is_match(id)),
+            Target::Method => self.regex.is_match(&item.method.method),
+        }
+    }
+}
+
+/// Split an optional leading HTTP method off a pattern like `GET /users/**`
+fn split_method(pattern: &str) -> (Option<String>, &str) {
+    let trimmed = pattern.trim();
+    if let Some((first, rest)) = trimmed.split_once(char::is_whitespace) {
+        if HTTP_METHODS.iter().any(|m| m.eq_ignore_ascii_case(first)) {
+            return (Some(first.to_lowercase()), rest.trim_start());
+        }
+    }
+    (None, trimmed)
+}
+
+/// Translate a glob into an anchored regex.
+/// For paths `*` stays within one segment and `**` crosses segments; elsewhere `*` matches anything.
+pub fn glob_to_regex(glob: &str, segmented: bool) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' if segmented => regex.push_str("[^/]*"),
+            '*' => regex.push_str(".*"),
+            '?' if segmented => regex.push_str("[^/]"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+impl SelectionArgs {
+    /// Whether any selection or exclusion pattern was given, which switches to non-interactive mode
+    pub fn is_active(&self) -> bool {
+        self.has_selectors()
+            || !(self.exclude.is_empty()
+                && self.exclude_method.is_empty()
+                && self.exclude_tag.is_empty())
+    }
+
+    /// Whether any pattern was given that adds operations to the selection
+    pub fn has_selectors(&self) -> bool {
+        !(self.select.is_empty()
+            && self.select_regex.is_empty()
+            && self.select_webhook.is_empty()
+            && self.select_tag.is_empty()
+            && self.select_operation.is_empty())
+    }
+
+    fn compile(&self) -> Result<(Vec<Pattern>, Vec<Pattern>)> {
+        let mut selectors = Vec::new();
+        for pattern in &self.select {
+            selectors.push(Pattern::path_glob("--select", pattern)?);
+        }
+        for pattern in &self.select_regex {
+            selectors.push(Pattern::path_regex("--select-regex", pattern)?);
+        }
+        for pattern in &self.select_webhook {
+            selectors.push(Pattern::webhook_glob("--select-webhook", pattern)?);
+        }
+        for pattern in &self.select_tag {
+            selectors.push(Pattern::glob("--select-tag", pattern, Target::Tag)?);
+        }
+        for pattern in &self.select_operation {
+            selectors.push(Pattern::glob(
+                "--select-operation",
+                pattern,
+                Target::OperationId,
+            )?);
+        }
+
+        let mut exclusions = Vec::new();
+        for pattern in &self.exclude {
+            exclusions.push(Pattern::path_glob("--exclude", pattern)?);
+        }
+        for method in &self.exclude_method {
+            exclusions.push(Pattern::new(
+                "--exclude-method",
+                method,
+                Target::Method,
+                &format!("(?i)^{}$", regex::escape(method)),
+            )?);
+        }
+        for pattern in &self.exclude_tag {
+            exclusions.push(Pattern::glob("--exclude-tag", pattern, Target::Tag)?);
+        }
+
+        Ok((selectors, exclusions))
+    }
+
+    /// Mark every operation matched by a selector as selected, then deselect every
+    /// operation matched by an exclusion, including ones selected beforehand by a recipe.
+    /// Returns the number of selected operations and fails when a selector matches nothing.
+    pub fn apply(&self, items: &mut [Endpoint]) -> Result<usize> {
+        let (selectors, exclusions) = self.compile()?;
+
+        let unmatched: Vec<&str> = selectors
+            .iter()
+            .filter(|pattern| !items.iter().any(|item| pattern.matches(item)))
+            .map(|pattern| pattern.source.as_str())
+            .collect();
+        if !unmatched.is_empty() {
+            return Err(eyre::eyre!("No operations match {}", unmatched.join(", ")));
+        }
+
+        for item in items.iter_mut() {
+            if exclusions.iter().any(|pattern| pattern.matches(item)) {
+                item.status = Status::Unselected;
+            } else if selectors.iter().any(|pattern| pattern.matches(item)) {
+                item.status = Status::Selected;
+            }
+        }
+        Ok(items
+            .iter()
+            .filter(|item| item.status == Status::Selected)
+            .count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_matches(glob: &str, segmented: bool, text: &str) -> bool {
+        Regex::new(&glob_to_regex(glob, segmented))
+            .unwrap()
+            .is_match(text)
+    }
+
+    #[test]
+    fn single_star_stays_within_a_segment_when_segmented() {
+        assert!(glob_matches("/users/*", true, "/users/{id}"));
+        assert!(!glob_matches("/users/*", true, "/users/{id}/orders"));
+        assert!(glob_matches("/users/*", false, "/users/{id}/orders"));
+    }
+
+    #[test]
+    fn double_star_crosses_segments() {
+        assert!(glob_matches("/users/**", true, "/users/{id}/orders"));
+        assert!(glob_matches("**/orders", true, "/users/{id}/orders"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(glob_matches("/v?/pets", true, "/v2/pets"));
+        assert!(!glob_matches("/v?/pets", true, "/v10/pets"));
+        assert!(!glob_matches("/a?b", true, "/a/b"));
+    }
+
+    #[test]
+    fn globs_are_anchored_and_literal() {
+        assert!(glob_matches("/v1.0/{id}", true, "/v1.0/{id}"));
+        assert!(!glob_matches("/v1.0/{id}", true, "/v1x0/{id}"));
+        assert!(!glob_matches("/users", true, "/users/{id}"));
+        assert!(!glob_matches("create*", false, "recreate"));
+    }
+}
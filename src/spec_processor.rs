@@ -1,11 +1,83 @@
-use color_eyre::eyre::{OptionExt, Result};
+use color_eyre::eyre::Result;
 use itertools::Itertools;
+use percent_encoding::percent_decode_str;
 use serde_yaml::{Mapping, Value};
+use std::cmp::Ordering;
 use std::collections::HashSet;
 
+use crate::diagnostic::SpecError;
+
+/// HTTP methods that can appear as operations in a path item
+pub const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Guards against `$ref` chains that keep pointing at each other
+const MAX_REF_DEPTH: usize = 32;
+
+/// Top-level sections holding reusable components in a Swagger 2.0 specification
+pub const SWAGGER2_COMPONENT_SECTIONS: [&str; 4] = [
+    "definitions",
+    "parameters",
+    "responses",
+    "securityDefinitions",
+];
+
+/// The specification flavour, which determines where reusable components live
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SpecVersion {
+    Swagger2,
+    OpenApi3,
+}
+
+impl SpecVersion {
+    /// Name of the section that holds the security scheme definitions
+    pub const fn security_section(self) -> &'static str {
+        match self {
+            Self::Swagger2 => "securityDefinitions",
+            Self::OpenApi3 => "securitySchemes",
+        }
+    }
+}
+
+/// Detect the specification version from the `swagger` or `openapi` field
+pub fn detect_spec_version(spec: &Mapping) -> SpecVersion {
+    let swagger = spec.get(Value::String("swagger".to_string()));
+    let is_swagger2 = match swagger {
+        Some(Value::String(version)) => version.starts_with('2'),
+        Some(Value::Number(version)) => version.as_f64().is_some_and(|v| (2.0..3.0).contains(&v)),
+        _ => false,
+    };
+    if is_swagger2 {
+        SpecVersion::Swagger2
+    } else {
+        SpecVersion::OpenApi3
+    }
+}
+
+/// Whether an operation lives under `paths` or under the OpenAPI 3.1 `webhooks`
+#[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum EndpointKind {
+    #[default]
+    Path,
+    Webhook,
+}
+
+impl EndpointKind {
+    /// The top-level section holding path items of this kind
+    pub const fn section(self) -> &'static str {
+        match self {
+            Self::Path => "paths",
+            Self::Webhook => "webhooks",
+        }
+    }
+}
+
+/// A single operation (one HTTP method on one path or webhook) as shown in the table
 #[derive(Default, Clone)]
 pub struct Endpoint {
-    pub methods: Vec<Method>,
+    pub kind: EndpointKind,
+    pub method: Method,
     pub path: String,
     pub description: String,
     pub refs: Vec<String>,
@@ -24,43 +96,245 @@ pub enum Status {
 pub struct Method {
     pub method: String,
     pub description: String,
+    pub operation_id: Option<String>,
+    pub tags: Vec<String>,
+    pub deprecated: bool,
+}
+
+/// Identifies an operation independently of its position in the table
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct OperationKey {
+    pub kind: EndpointKind,
+    pub path: String,
+    pub method: String,
+}
+
+impl OperationKey {
+    /// Human readable form such as `GET /users` or `POST webhook newPet`
+    pub fn describe(&self) -> String {
+        match self.kind {
+            EndpointKind::Path => format!("{} {}", self.method.to_uppercase(), self.path),
+            EndpointKind::Webhook => {
+                format!("{} webhook {}", self.method.to_uppercase(), self.path)
+            }
+        }
+    }
+}
+
+impl Endpoint {
+    pub fn key(&self) -> OperationKey {
+        OperationKey {
+            kind: self.kind,
+            path: self.path.clone(),
+            method: self.method.method.clone(),
+        }
+    }
+
+    pub fn has_key(&self, key: &OperationKey) -> bool {
+        self.kind == key.kind && self.path == key.path && self.method.method == key.method
+    }
+
+    /// Order paths before webhooks, then by path and by method
+    pub fn cmp_location(&self, other: &Self) -> Ordering {
+        self.kind
+            .cmp(&other.kind)
+            .then_with(|| self.path.cmp(&other.path))
+            .then_with(|| method_rank(&self.method.method).cmp(&method_rank(&other.method.method)))
+    }
+
+    /// The operation summary, falling back to the path item summary
+    pub fn summary(&self) -> &str {
+        if self.method.description.is_empty() {
+            &self.description
+        } else {
+            &self.method.description
+        }
+    }
+}
+
+/// Position of a method in the canonical OpenAPI ordering, unknown methods last
+pub fn method_rank(method: &str) -> usize {
+    HTTP_METHODS
+        .iter()
+        .position(|m| m.eq_ignore_ascii_case(method))
+        .unwrap_or(HTTP_METHODS.len())
+}
+
+/// Split a JSON Pointer (RFC 6901) in URI fragment form into its unescaped segments
+pub fn pointer_segments(fragment: &str) -> Vec<String> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    percent_decode_str(fragment)
+        .decode_utf8_lossy()
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+/// Escape a single segment for use in a JSON Pointer
+pub fn escape_pointer_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn pointer_step<'a>(value: &'a Value, segment: &str) -> Option<&'a Value> {
+    match value {
+        Value::Mapping(map) => map.get(segment),
+        Value::Sequence(seq) => segment.parse::<usize>().ok().and_then(|i| seq.get(i)),
+        _ => None,
+    }
+}
+
+/// Resolve a JSON Pointer fragment inside a document
+pub fn resolve_pointer<'a>(document: &'a Value, fragment: &str) -> Option<&'a Value> {
+    pointer_segments(fragment)
+        .iter()
+        .try_fold(document, |value, segment| pointer_step(value, segment))
+}
+
+/// Resolve a local reference such as `#/paths/~1users/get/requestBody` inside the specification
+pub fn resolve_local_ref<'a>(spec: &'a Mapping, reference: &str) -> Option<&'a Value> {
+    let segments = pointer_segments(reference.strip_prefix('#')?);
+    let (first, rest) = segments.split_first()?;
+    rest.iter()
+        .try_fold(spec.get(first.as_str())?, |value, segment| {
+            pointer_step(value, segment)
+        })
+}
+
+/// Replace the `$ref` entry of a mapping with the entries of its target.
+/// Sibling fields of the `$ref` take precedence and the original key order is kept.
+pub fn splice_ref_target(map: &Mapping, target: &Mapping) -> Mapping {
+    let mut spliced = Mapping::new();
+    for (key, value) in map {
+        if key.as_str() == Some("$ref") {
+            for (target_key, target_value) in target {
+                if !map.contains_key(target_key) {
+                    spliced.insert(target_key.clone(), target_value.clone());
+                }
+            }
+        } else {
+            spliced.insert(key.clone(), value.clone());
+        }
+    }
+    spliced
+}
+
+/// Follow local path item `$ref`s such as `#/paths/~1bar`, merging in the referenced path item
+fn resolve_path_item(spec: &Mapping, path_item: &Mapping) -> Mapping {
+    let mut resolved = path_item.clone();
+    for _ in 0..MAX_REF_DEPTH {
+        let Some(target) = resolved
+            .get(Value::String("$ref".to_string()))
+            .and_then(|r| r.as_str())
+            .and_then(|r| resolve_local_ref(spec, r))
+            .and_then(|target| target.as_mapping())
+        else {
+            break;
+        };
+        resolved = splice_ref_target(&resolved, target);
+    }
+    resolved
 }
 
-pub fn fetch_endpoints_from_spec(spec: &Mapping) -> Vec<Endpoint> {
+/// Collect the operations of all path items and webhooks.
+/// Malformed entries are skipped and reported instead of aborting, so the valid ones can still be used.
+pub fn fetch_endpoints_from_spec(spec: &Mapping) -> (Vec<Endpoint>, Vec<SpecError>) {
     let mut table_items: Vec<Endpoint> = Vec::new();
-    let paths = spec
-        .get(Value::String("paths".to_string()))
-        .and_then(|v| v.as_mapping())
-        .ok_or_eyre("No 'paths' field found or it's not a mapping")
-        .unwrap();
+    let mut errors: Vec<SpecError> = Vec::new();
 
-    for (path, ops) in paths {
-        let path_str = path
-            .as_str()
-            .ok_or_eyre("Path key is not a string")
-            .unwrap();
-        let mut table_item = Endpoint::default();
-        let ops_map = ops
-            .as_mapping()
-            .ok_or_eyre(format!("Operations for '{}' not a mapping", path_str))
-            .unwrap();
-        let mut refs: Vec<String> = Vec::new();
+    // OpenAPI 3.1 documents may only describe webhooks
+    let mut sections = Vec::new();
+    for kind in [EndpointKind::Path, EndpointKind::Webhook] {
+        match spec.get(Value::String(kind.section().to_string())) {
+            Some(Value::Mapping(section)) => sections.push((kind, section)),
+            Some(_) => errors.push(SpecError::new(
+                format!("/{}", kind.section()),
+                format!("'{}' is not a mapping", kind.section()),
+            )),
+            None => {}
+        }
+    }
+    if sections.is_empty() && errors.is_empty() {
+        errors.push(SpecError::new("/paths", "No 'paths' or 'webhooks' found"));
+    }
+
+    for (kind, section) in sections {
+        fetch_path_items(spec, section, kind, &mut table_items, &mut errors);
+    }
+
+    // Order table items by kind, path and method
+    table_items.sort_by(Endpoint::cmp_location);
+    (table_items, errors)
+}
+
+fn fetch_path_items(
+    spec: &Mapping,
+    path_items: &Mapping,
+    kind: EndpointKind,
+    table_items: &mut Vec<Endpoint>,
+    errors: &mut Vec<SpecError>,
+) {
+    for (path, ops) in path_items {
+        let Some(path_str) = path.as_str() else {
+            let key = serde_yaml::to_string(path).unwrap_or_default();
+            errors.push(SpecError::new(
+                format!("/{}/{}", kind.section(), escape_pointer_segment(key.trim())),
+                "Path key is not a string",
+            ));
+            continue;
+        };
+        let pointer = format!("/{}/{}", kind.section(), escape_pointer_segment(path_str));
+        let Some(ops_map) = ops.as_mapping() else {
+            errors.push(SpecError::new(pointer, "Path item is not a mapping"));
+            continue;
+        };
+        if let Some(reference) = ops_map
+            .get(Value::String("$ref".to_string()))
+            .and_then(|r| r.as_str())
+            .filter(|r| r.starts_with('#') && resolve_local_ref(spec, r).is_none())
+        {
+            errors.push(SpecError::new(
+                format!("{}/$ref", pointer),
+                format!("Could not resolve '{}'", reference),
+            ));
+        }
+        let ops_map = &resolve_path_item(spec, ops_map);
+
+        // Path item summary, description and parameters are shared by all operations
+        let path_description = ops_map
+            .get(Value::String("summary".to_string()))
+            .or_else(|| ops_map.get(Value::String("description".to_string())))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let path_parameters = ops_map
+            .get(Value::String("parameters".to_string()))
+            .and_then(|v| v.as_sequence())
+            .map_or(&[][..], Vec::as_slice);
+
+        // Only the HTTP methods are operations; `servers`, `$ref` and `x-*` extensions are not
         for (ops_method, op) in ops_map {
-            let method_str = ops_method
+            let Some(method_str) = ops_method
                 .as_str()
-                .ok_or_eyre("Method key is not a string")
-                .unwrap();
-                        
-            if method_str == "summary" {
-                table_item.description = op.as_str().unwrap_or("").to_string();
+                .filter(|method| HTTP_METHODS.contains(method))
+            else {
                 continue;
-            }
-            if method_str == "description" && table_item.description.is_empty() {
-                table_item.description = op.as_str().unwrap_or("").to_string();
+            };
+            if !op.is_mapping() {
+                errors.push(SpecError::new(
+                    format!("{}/{}", pointer, method_str),
+                    "Operation is not a mapping",
+                ));
                 continue;
             }
-            
-            let mut method = Method::default();
+
+            let mut table_item = Endpoint {
+                kind,
+                path: path_str.to_string(),
+                description: path_description.clone(),
+                ..Default::default()
+            };
+
             let summary = op
                 .as_mapping()
                 .and_then(|m| m.get(Value::String("summary".to_string())))
@@ -70,37 +344,120 @@ pub fn fetch_endpoints_from_spec(spec: &Mapping) -> Vec<Endpoint> {
                 .as_mapping()
                 .and_then(|m| m.get(Value::String("description".to_string())))
                 .and_then(|v| v.as_str())
-                .unwrap_or("No description");
-            
-            // Check for operation-level parameters
+                .unwrap_or("");
+
+            // Merge path-level and operation-level parameters, then read operationId and tags
+            let op_parameters = op
+                .as_mapping()
+                .and_then(|m| m.get(Value::String("parameters".to_string())))
+                .and_then(|v| v.as_sequence())
+                .map_or(&[][..], Vec::as_slice);
+            let parameters = merge_parameters(path_parameters, op_parameters);
+            extract_parameters(&parameters, &mut table_item.parameters);
+
             if let Some(op_map) = op.as_mapping() {
-                if let Some(params) = op_map.get(Value::String("parameters".to_string())) {
-                    if let Some(params_array) = params.as_sequence() {
-                        extract_parameters(params_array, &mut table_item.parameters);
-                    }
-                }
+                table_item.method.operation_id = op_map
+                    .get(Value::String("operationId".to_string()))
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string);
+                table_item.method.tags = op_map
+                    .get(Value::String("tags".to_string()))
+                    .and_then(|v| v.as_sequence())
+                    .map(|tags| {
+                        tags.iter()
+                            .filter_map(|tag| tag.as_str().map(str::to_string))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                table_item.method.deprecated = op_map
+                    .get(Value::String("deprecated".to_string()))
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
             }
-            
-            method.method = method_str.to_string();
-            method.description = if !summary.is_empty() {
-                summary.to_string()
-            } else {
+
+            table_item.method.method = method_str.to_string();
+            table_item.method.description = if summary.is_empty() {
                 description.to_string()
+            } else {
+                summary.to_string()
             };
-            refs.extend(fetch_all_references(op));
-            table_item.methods.push(method);
+            let mut refs = fetch_all_references(op);
+            refs.extend(path_parameters.iter().flat_map(fetch_all_references));
+            table_item.refs = strip_path_from_references(&refs)
+                .into_iter()
+                .unique()
+                .collect();
+            table_items.push(table_item);
         }
-        table_item.path = path_str.to_string();
-        table_item.refs = strip_path_from_references(&refs)
-            .into_iter()
-            .unique()
-            .collect();
-        table_items.push(table_item);
     }
+}
 
-    // Order table items by path
-    table_items.sort_by(|a, b| a.path.cmp(&b.path));
-    table_items
+/// Combine path-level and operation-level parameters.
+/// An operation parameter overrides a path parameter with the same name and location.
+fn merge_parameters(path_parameters: &[Value], op_parameters: &[Value]) -> Vec<Value> {
+    let identity = |param: &Value| {
+        let param = param.as_mapping()?;
+        if let Some(reference) = param.get(Value::String("$ref".to_string())) {
+            return Some((reference.as_str()?.to_string(), String::new()));
+        }
+        Some((
+            param
+                .get(Value::String("name".to_string()))?
+                .as_str()?
+                .to_string(),
+            param
+                .get(Value::String("in".to_string()))
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+        ))
+    };
+    let overridden: HashSet<(String, String)> = op_parameters.iter().filter_map(identity).collect();
+
+    path_parameters
+        .iter()
+        .filter(|param| identity(param).is_none_or(|id| !overridden.contains(&id)))
+        .chain(op_parameters)
+        .cloned()
+        .collect()
+}
+
+/// Mark every operation that appears in a previously written output as selected.
+/// Returns a description of each operation in that output that no longer exists.
+pub fn select_operations_from_output(previous: &Mapping, items: &mut [Endpoint]) -> Vec<String> {
+    let mut missing = Vec::new();
+    for kind in [EndpointKind::Path, EndpointKind::Webhook] {
+        let Some(path_items) = previous
+            .get(Value::String(kind.section().to_string()))
+            .and_then(|v| v.as_mapping())
+        else {
+            continue;
+        };
+
+        for (path, path_item) in path_items {
+            let (Some(path_str), Some(path_map)) = (path.as_str(), path_item.as_mapping()) else {
+                continue;
+            };
+            for method in path_map.keys().filter_map(|key| key.as_str()) {
+                if !HTTP_METHODS.contains(&method) {
+                    continue;
+                }
+                let key = OperationKey {
+                    kind,
+                    path: path_str.to_string(),
+                    method: method.to_string(),
+                };
+                match items.iter_mut().find(|item| item.has_key(&key)) {
+                    Some(item) => item.status = Status::Selected,
+                    None => missing.push(format!(
+                        "{} from the existing output no longer exists in the specification",
+                        key.describe()
+                    )),
+                }
+            }
+        }
+    }
+    missing
 }
 
 // Helper function to extract parameter names from a parameters array
@@ -111,30 +468,40 @@ fn extract_parameters(params_array: &[Value], parameters: &mut Vec<String>) {
             if let Some(name_value) = param_map.get(Value::String("name".to_string())) {
                 if let Some(name) = name_value.as_str() {
                     // Get the parameter location (in)
-                    let prefix = if let Some(in_value) = param_map.get(Value::String("in".to_string())) {
-                        if let Some(in_type) = in_value.as_str() {
-                            match in_type {
-                                "path" => "/",
-                                "body" => "body:",
-                                "query" => "?",
-                                _ => "",
-                            }
-                        } else {
-                            ""
-                        }
+                    let in_type = param_map
+                        .get(Value::String("in".to_string()))
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    let prefix = match in_type {
+                        "path" => "/",
+                        "body" => "body:",
+                        "formData" => "form:",
+                        "query" => "?",
+                        _ => "",
+                    };
+
+                    // Swagger 2.0 body parameters are better described by their schema
+                    let label = if in_type == "body" {
+                        param_map
+                            .get(Value::String("schema".to_string()))
+                            .map(fetch_all_references)
+                            .and_then(|refs| strip_path_from_references(&refs).into_iter().next())
+                            .unwrap_or_else(|| name.to_string())
                     } else {
-                        ""
+                        name.to_string()
                     };
-                    
+
                     // Add prefixed parameter name
-                    parameters.push(format!("{}{}", prefix, name));
+                    parameters.push(format!("{}{}", prefix, label));
                 }
             }
         }
     }
 }
 
-/// Recursively fetch all $ref values from a Value tree
+/// Recursively fetch all references from a Value tree.
+/// Besides `$ref`, this covers `discriminator.mapping` values.
+/// `links.operationRef` points at operations rather than components, see `drop_dangling_links`.
 fn fetch_all_references(value: &Value) -> Vec<String> {
     let mut refs = Vec::new();
     match value {
@@ -143,6 +510,21 @@ fn fetch_all_references(value: &Value) -> Vec<String> {
             if let Some(Value::String(ref_str)) = map.get(Value::String("$ref".to_string())) {
                 refs.push(ref_str.clone());
             }
+            // Discriminator mappings name schemas by reference or by bare schema name
+            if let Some(mapping) = map
+                .get(Value::String("discriminator".to_string()))
+                .and_then(|d| d.as_mapping())
+                .and_then(|d| d.get(Value::String("mapping".to_string())))
+                .and_then(|m| m.as_mapping())
+            {
+                for target in mapping.values().filter_map(|v| v.as_str()) {
+                    if target.contains('/') || target.contains('#') {
+                        refs.push(target.to_string());
+                    } else {
+                        refs.push(format!("#/components/schemas/{}", target));
+                    }
+                }
+            }
             // Recurse into all values in the mapping
             for (_, v) in map {
                 refs.extend(fetch_all_references(v));
@@ -162,22 +544,30 @@ fn fetch_all_references(value: &Value) -> Vec<String> {
 fn strip_path_from_references(references: &[String]) -> Vec<String> {
     references
         .iter()
-        .map(|ref_str| ref_str.split('/').next_back().unwrap().to_string())
+        .map(|ref_str| {
+            parse_component_ref(ref_str).map_or_else(
+                || ref_str.split('/').next_back().unwrap().to_string(),
+                |(_, name)| name,
+            )
+        })
         .collect::<Vec<String>>()
 }
 
 /// Extract component name and type from a $ref string
-/// Returns (component_type, component_name) or None if not a component reference
+/// Returns (component_type, component_name) or None if not a component reference.
+/// Swagger 2.0 references such as `#/definitions/Pet` use the top-level section as type.
+/// References into a component, like `#/components/schemas/Pet/properties/id`, name the component.
 fn parse_component_ref(ref_str: &str) -> Option<(String, String)> {
-    if ref_str.starts_with("#/components/") {
-        let parts: Vec<&str> = ref_str.split('/').collect();
-        if parts.len() >= 4 {
-            let component_type = parts[2].to_string();
-            let component_name = parts[3..].join("/");
-            return Some((component_type, component_name));
+    let segments = pointer_segments(ref_str.strip_prefix('#')?);
+    match segments.as_slice() {
+        [components, component_type, component_name, ..] if components == "components" => {
+            Some((component_type.clone(), component_name.clone()))
         }
+        [section, name, ..] if SWAGGER2_COMPONENT_SECTIONS.contains(&section.as_str()) => {
+            Some((section.clone(), name.clone()))
+        }
+        _ => None,
     }
-    None
 }
 
 /// Recursively collect all transitive component references
@@ -187,12 +577,15 @@ fn collect_transitive_references(
     initial_refs: &[String],
 ) -> HashSet<(String, String)> {
     let mut all_refs = HashSet::new();
+    // Components referenced other than as the `allOf` base of a subtype, i.e. used polymorphically
+    let mut direct_refs = HashSet::new();
     let mut to_process: Vec<(String, String)> = Vec::new();
 
     // Parse initial references
     for ref_str in initial_refs {
         if let Some((comp_type, comp_name)) = parse_component_ref(ref_str) {
             let key = (comp_type, comp_name);
+            direct_refs.insert(key.clone());
             if all_refs.insert(key.clone()) {
                 to_process.push(key);
             }
@@ -204,10 +597,19 @@ fn collect_transitive_references(
         if let Some(comp_section) = components.get(Value::String(comp_type.clone())) {
             if let Some(comp_mapping) = comp_section.as_mapping() {
                 if let Some(comp_value) = comp_mapping.get(Value::String(comp_name.clone())) {
+                    let mut bases = all_of_bases(comp_value);
                     // Extract all references from this component
                     for nested_ref in fetch_all_references(comp_value) {
                         if let Some((nested_type, nested_name)) = parse_component_ref(&nested_ref) {
                             let key = (nested_type.clone(), nested_name.clone());
+                            match bases.iter().position(|base| *base == key) {
+                                Some(index) => {
+                                    bases.swap_remove(index);
+                                }
+                                None => {
+                                    direct_refs.insert(key.clone());
+                                }
+                            }
                             if all_refs.insert(key.clone()) {
                                 to_process.push(key);
                             }
@@ -216,11 +618,94 @@ fn collect_transitive_references(
                 }
             }
         }
+
+        // Subtypes of a polymorphic schema only refer to their base, never the other way around.
+        // A base that is only reached through a subtype's own `allOf` does not need its siblings.
+        if to_process.is_empty() {
+            to_process = find_discriminator_subtypes(components, &all_refs, &direct_refs);
+            all_refs.extend(to_process.iter().cloned());
+        }
     }
 
     all_refs
 }
 
+/// Find schemas that extend a directly referenced schema with a discriminator through `allOf`
+fn find_discriminator_subtypes(
+    components: &Mapping,
+    collected: &HashSet<(String, String)>,
+    direct: &HashSet<(String, String)>,
+) -> Vec<(String, String)> {
+    let mut subtypes = Vec::new();
+    for section_name in ["schemas", "definitions"] {
+        let Some(section) = components
+            .get(Value::String(section_name.to_string()))
+            .and_then(|v| v.as_mapping())
+        else {
+            continue;
+        };
+        for (name, schema) in section {
+            let Some(name) = name.as_str() else {
+                continue;
+            };
+            let key = (section_name.to_string(), name.to_string());
+            if collected.contains(&key) {
+                continue;
+            }
+            let extends_direct_base = all_of_bases(schema).into_iter().any(|base| {
+                direct.contains(&base)
+                    && inherits_discriminator(components, &base, &mut HashSet::new())
+            });
+            if extends_direct_base {
+                subtypes.push(key);
+            }
+        }
+    }
+    subtypes
+}
+
+/// The component references listed in a schema's `allOf`
+fn all_of_bases(schema: &Value) -> Vec<(String, String)> {
+    schema
+        .as_mapping()
+        .and_then(|schema| schema.get(Value::String("allOf".to_string())))
+        .and_then(|all_of| all_of.as_sequence())
+        .map(|all_of| {
+            all_of
+                .iter()
+                .filter_map(|item| item.as_mapping())
+                .filter_map(|item| item.get(Value::String("$ref".to_string())))
+                .filter_map(|r| r.as_str())
+                .filter_map(parse_component_ref)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether a schema declares a discriminator itself or inherits one through `allOf`
+fn inherits_discriminator(
+    components: &Mapping,
+    key: &(String, String),
+    visited: &mut HashSet<(String, String)>,
+) -> bool {
+    if !visited.insert(key.clone()) {
+        return false;
+    }
+    let Some(schema) = components
+        .get(Value::String(key.0.clone()))
+        .and_then(|section| section.as_mapping())
+        .and_then(|section| section.get(Value::String(key.1.clone())))
+    else {
+        return false;
+    };
+    schema
+        .as_mapping()
+        .is_some_and(|schema| schema.contains_key(Value::String("discriminator".to_string())))
+        || all_of_bases(schema)
+            .iter()
+            .any(|base| inherits_discriminator(components, base, visited))
+}
+
 /// Extract security scheme names from security requirements
 fn extract_security_schemes(value: &Value) -> Vec<String> {
     let mut schemes = Vec::new();
@@ -248,38 +733,98 @@ fn extract_security_schemes(value: &Value) -> Vec<String> {
     schemes
 }
 
-pub fn process_spec_for_output(spec: &Mapping, selected_items: &[&Endpoint]) -> Result<Mapping> {
-    let original_path_specifications = spec
-        .get(Value::String("paths".to_string()))
+/// Copy a path item, keeping only the selected operations and all path-level fields
+fn filter_path_item(path_item: &Mapping, selected_methods: &HashSet<&str>) -> Mapping {
+    path_item
+        .iter()
+        .filter(|(key, _)| {
+            let key_str = key.as_str().unwrap_or("");
+            !HTTP_METHODS.contains(&key_str) || selected_methods.contains(key_str)
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// Copy a `paths` or `webhooks` section, keeping only path items with a selected operation
+fn filter_path_items(spec: &Mapping, kind: EndpointKind, selected_items: &[&Endpoint]) -> Mapping {
+    let empty_section = Mapping::new();
+    let original_path_items = spec
+        .get(Value::String(kind.section().to_string()))
         .and_then(|v| v.as_mapping())
-        .unwrap();
+        .unwrap_or(&empty_section);
 
-    // Create paths mapping with only selected paths
-    let mut paths = Mapping::new();
-    for item in selected_items {
-        if let Some(path_data) = original_path_specifications.get(Value::String(item.path.clone()))
-        {
-            paths.insert(Value::String(item.path.clone()), path_data.clone());
+    // Keep only the selected operations, in the original order
+    let mut path_items = Mapping::new();
+    for (path_key, path_data) in original_path_items {
+        let Some(path_str) = path_key.as_str() else {
+            continue;
+        };
+        let selected_methods: HashSet<&str> = selected_items
+            .iter()
+            .filter(|item| item.kind == kind && item.path == path_str)
+            .map(|item| item.method.method.as_str())
+            .collect();
+        if selected_methods.is_empty() {
+            continue;
+        }
+        if let Some(path_map) = path_data.as_mapping() {
+            let resolved = resolve_path_item(spec, path_map);
+            let mut filtered = Value::Mapping(filter_path_item(&resolved, &selected_methods));
+            inline_path_refs(&mut filtered, spec, 0);
+            path_items.insert(path_key.clone(), filtered);
         }
     }
+    path_items
+}
 
-    // Collect all $ref references from selected paths
-    let initial_refs: Vec<String> = selected_items
-        .iter()
-        .filter_map(|item| original_path_specifications.get(Value::String(item.path.clone())))
+/// Inline references that point into `paths` or `webhooks`, since their target may be snipped
+fn inline_path_refs(value: &mut Value, spec: &Mapping, depth: usize) {
+    match value {
+        Value::Mapping(map) => {
+            let target = map
+                .get(Value::String("$ref".to_string()))
+                .and_then(|r| r.as_str())
+                .filter(|r| r.starts_with("#/paths/") || r.starts_with("#/webhooks/"))
+                .and_then(|r| resolve_local_ref(spec, r))
+                .and_then(|target| target.as_mapping());
+            if let Some(target) = target {
+                if depth < MAX_REF_DEPTH {
+                    *map = splice_ref_target(map, target);
+                    inline_path_refs(value, spec, depth + 1);
+                }
+                return;
+            }
+            for child in map.values_mut() {
+                inline_path_refs(child, spec, depth);
+            }
+        }
+        Value::Sequence(seq) => {
+            for item in seq {
+                inline_path_refs(item, spec, depth);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn process_spec_for_output(spec: &Mapping, selected_items: &[&Endpoint]) -> Result<Mapping> {
+    let paths = filter_path_items(spec, EndpointKind::Path, selected_items);
+    let webhooks = filter_path_items(spec, EndpointKind::Webhook, selected_items);
+    let surviving_path_items = || paths.values().chain(webhooks.values());
+
+    // Collect all $ref references from the selected operations and their path items
+    let initial_refs: Vec<String> = surviving_path_items()
         .flat_map(fetch_all_references)
         .collect();
 
-    // Extract security scheme references from selected paths and top-level
+    // Extract security scheme references from selected operations and top-level
     let mut security_schemes = HashSet::new();
-    for item in selected_items {
-        if let Some(path_data) = original_path_specifications.get(Value::String(item.path.clone())) {
-            if let Some(path_map) = path_data.as_mapping() {
-                for op_value in path_map.values() {
-                    if let Some(op_map) = op_value.as_mapping() {
-                        if let Some(security) = op_map.get(Value::String("security".to_string())) {
-                            security_schemes.extend(extract_security_schemes(security));
-                        }
+    for path_data in surviving_path_items() {
+        if let Some(path_map) = path_data.as_mapping() {
+            for op_value in path_map.values() {
+                if let Some(op_map) = op_value.as_mapping() {
+                    if let Some(security) = op_map.get(Value::String("security".to_string())) {
+                        security_schemes.extend(extract_security_schemes(security));
                     }
                 }
             }
@@ -289,12 +834,33 @@ pub fn process_spec_for_output(spec: &Mapping, selected_items: &[&Endpoint]) ->
         security_schemes.extend(extract_security_schemes(security));
     }
 
-    // Get components section
+    // Tags carried by at least one surviving operation
+    let used_tags: HashSet<&str> = surviving_path_items()
+        .filter_map(|path_data| path_data.as_mapping())
+        .flat_map(|path_map| {
+            path_map
+                .iter()
+                .filter(|(key, _)| HTTP_METHODS.contains(&key.as_str().unwrap_or("")))
+                .map(|(_, op)| op)
+        })
+        .filter_map(|op| op.as_mapping())
+        .filter_map(|op| op.get(Value::String("tags".to_string())))
+        .filter_map(|tags| tags.as_sequence())
+        .flatten()
+        .filter_map(|tag| tag.as_str())
+        .collect();
+
+    let version = detect_spec_version(spec);
+
+    // Get components section; Swagger 2.0 keeps its component sections at the top level
     let empty_components = Mapping::new();
-    let components = spec
-        .get(Value::String("components".to_string()))
-        .and_then(|v| v.as_mapping())
-        .unwrap_or(&empty_components);
+    let components = match version {
+        SpecVersion::Swagger2 => spec,
+        SpecVersion::OpenApi3 => spec
+            .get(Value::String("components".to_string()))
+            .and_then(|v| v.as_mapping())
+            .unwrap_or(&empty_components),
+    };
 
     // Collect all transitive component references
     let all_component_refs = collect_transitive_references(components, &initial_refs);
@@ -308,41 +874,673 @@ pub fn process_spec_for_output(spec: &Mapping, selected_items: &[&Endpoint]) ->
     // Build the output in the original order
     for key in key_order {
         let value = spec.get(&key).unwrap();
-        if key.as_str() == Some("paths") {
+        let key_str = key.as_str().unwrap_or("");
+        if key_str == "paths" {
             // Replace paths with filtered version
             output.insert(key, Value::Mapping(paths.clone()));
-        } else if key.as_str() == Some("components") {
+        } else if key_str == "webhooks" {
+            // Replace webhooks with filtered version, dropping the section when none remain
+            if !webhooks.is_empty() {
+                output.insert(key, Value::Mapping(webhooks.clone()));
+            }
+        } else if version == SpecVersion::OpenApi3 && key_str == "components" {
             // Handle components section
             let mut components_output = Mapping::new();
             if let Some(components_map) = value.as_mapping() {
                 for (child_key, child_value) in components_map {
-                    let child_key_str = child_key.as_str().unwrap_or("");
-                    let mut filtered_section = Mapping::new();
-
-                    if let Some(section_map) = child_value.as_mapping() {
-                        for (item_key, item_value) in section_map {
-                            let item_key_str = item_key.as_str().unwrap_or("");
-                            let lookup_key = (child_key_str.to_string(), item_key_str.to_string());
-                            let should_include = all_component_refs.contains(&lookup_key)
-                                || (child_key_str == "securitySchemes" && security_schemes.contains(item_key_str));
-
-                            if should_include {
-                                filtered_section.insert(item_key.clone(), item_value.clone());
-                            }
-                        }
-                    }
+                    let filtered_section = filter_component_section(
+                        child_key.as_str().unwrap_or(""),
+                        child_value,
+                        &all_component_refs,
+                        &security_schemes,
+                        version,
+                    );
 
                     if !filtered_section.is_empty() {
-                        components_output.insert(child_key.clone(), Value::Mapping(filtered_section));
+                        components_output
+                            .insert(child_key.clone(), Value::Mapping(filtered_section));
                     }
                 }
             }
             output.insert(key, Value::Mapping(components_output));
+        } else if version == SpecVersion::Swagger2 && SWAGGER2_COMPONENT_SECTIONS.contains(&key_str)
+        {
+            // Handle top-level Swagger 2.0 component sections
+            let filtered_section = filter_component_section(
+                key_str,
+                value,
+                &all_component_refs,
+                &security_schemes,
+                version,
+            );
+
+            if !filtered_section.is_empty() {
+                output.insert(key, Value::Mapping(filtered_section));
+            }
+        } else if key_str == "tags" {
+            output.insert(key, filter_tags(value, &used_tags));
+        } else if key_str == "x-tagGroups" {
+            output.insert(key, filter_tag_groups(value, &used_tags));
         } else {
             // Copy other sections as-is
             output.insert(key, value.clone());
         }
     }
 
+    // Links to operations that were not kept would dangle
+    let kept = output.clone();
+    for value in output.values_mut() {
+        drop_dangling_links(value, &kept);
+    }
+
     Ok(output)
 }
+
+/// Remove links whose local `operationRef` points at an operation missing from `output`,
+/// including links that `$ref` a link in `components/links` that does.
+fn drop_dangling_links(value: &mut Value, output: &Mapping) {
+    match value {
+        Value::Mapping(map) => {
+            let links_key = Value::String("links".to_string());
+            if let Some(Value::Mapping(links)) = map.get_mut(&links_key) {
+                links.retain(|_, link| link_target_exists(output, link, 0));
+                if links.is_empty() {
+                    map.remove(&links_key);
+                }
+            }
+            for (_, v) in map.iter_mut() {
+                drop_dangling_links(v, output);
+            }
+        }
+        Value::Sequence(seq) => {
+            for item in seq {
+                drop_dangling_links(item, output);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Follows `$ref` chains between links, giving up on cycles
+fn link_target_exists(output: &Mapping, link: &Value, depth: usize) -> bool {
+    let Some(link) = link.as_mapping() else {
+        return true;
+    };
+    let local_ref = |key: &str| {
+        link.get(Value::String(key.to_string()))
+            .and_then(|v| v.as_str())
+            .filter(|reference| reference.starts_with('#'))
+    };
+    if let Some(reference) = local_ref("$ref") {
+        return depth < 16
+            && resolve_local_ref(output, reference)
+                .is_some_and(|target| link_target_exists(output, target, depth + 1));
+    }
+    local_ref("operationRef").is_none_or(|reference| resolve_local_ref(output, reference).is_some())
+}
+
+/// Keep only the tag definitions that a surviving operation uses, in their original order
+fn filter_tags(tags: &Value, used_tags: &HashSet<&str>) -> Value {
+    let Some(tags) = tags.as_sequence() else {
+        return tags.clone();
+    };
+    Value::Sequence(
+        tags.iter()
+            .filter(|tag| {
+                tag.as_mapping()
+                    .and_then(|tag| tag.get(Value::String("name".to_string())))
+                    .and_then(|name| name.as_str())
+                    .is_some_and(|name| used_tags.contains(name))
+            })
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Trim the tags listed in each Redoc tag group and drop groups that end up empty
+fn filter_tag_groups(groups: &Value, used_tags: &HashSet<&str>) -> Value {
+    let Some(groups) = groups.as_sequence() else {
+        return groups.clone();
+    };
+    Value::Sequence(
+        groups
+            .iter()
+            .filter_map(|group| {
+                let mut group = group.as_mapping()?.clone();
+                let tags: Vec<Value> = group
+                    .get(Value::String("tags".to_string()))?
+                    .as_sequence()?
+                    .iter()
+                    .filter(|tag| tag.as_str().is_some_and(|tag| used_tags.contains(tag)))
+                    .cloned()
+                    .collect();
+                if tags.is_empty() {
+                    return None;
+                }
+                group.insert(Value::String("tags".to_string()), Value::Sequence(tags));
+                Some(Value::Mapping(group))
+            })
+            .collect(),
+    )
+}
+
+/// Keep only the referenced entries of a component section such as `schemas` or `definitions`
+fn filter_component_section(
+    section_name: &str,
+    section: &Value,
+    component_refs: &HashSet<(String, String)>,
+    security_schemes: &HashSet<String>,
+    version: SpecVersion,
+) -> Mapping {
+    let mut filtered_section = Mapping::new();
+
+    if let Some(section_map) = section.as_mapping() {
+        for (item_key, item_value) in section_map {
+            let item_key_str = item_key.as_str().unwrap_or("");
+            let lookup_key = (section_name.to_string(), item_key_str.to_string());
+            let should_include = component_refs.contains(&lookup_key)
+                || (section_name == version.security_section()
+                    && security_schemes.contains(item_key_str));
+
+            if should_include {
+                filtered_section.insert(item_key.clone(), item_value.clone());
+            }
+        }
+    }
+
+    filtered_section
+}
+
+/// How many entries of a section survive in the output
+pub struct SectionCount {
+    pub name: String,
+    pub kept: usize,
+    pub total: usize,
+}
+
+/// Compare the input and output specification section by section
+pub fn count_sections(spec: &Mapping, output: &Mapping) -> Vec<SectionCount> {
+    let entries = |root: &Mapping, keys: &[&str]| {
+        keys.iter()
+            .try_fold(root, |map, key| {
+                map.get(Value::String(key.to_string()))?.as_mapping()
+            })
+            .map_or(0, Mapping::len)
+    };
+    let operations = |root: &Mapping, kind: EndpointKind| -> usize {
+        root.get(Value::String(kind.section().to_string()))
+            .and_then(|v| v.as_mapping())
+            .into_iter()
+            .flat_map(Mapping::values)
+            .filter_map(|path_item| path_item.as_mapping())
+            .map(|path_item| {
+                resolve_path_item(root, path_item)
+                    .keys()
+                    .filter(|key| HTTP_METHODS.contains(&key.as_str().unwrap_or("")))
+                    .count()
+            })
+            .sum()
+    };
+    let count = |name: &str, kept: usize, total: usize| SectionCount {
+        name: name.to_string(),
+        kept,
+        total,
+    };
+
+    let mut counts = vec![
+        count(
+            "paths",
+            entries(output, &["paths"]),
+            entries(spec, &["paths"]),
+        ),
+        count(
+            "operations",
+            operations(output, EndpointKind::Path),
+            operations(spec, EndpointKind::Path),
+        ),
+    ];
+    if spec.contains_key(Value::String("webhooks".to_string())) {
+        counts.push(count(
+            "webhook operations",
+            operations(output, EndpointKind::Webhook),
+            operations(spec, EndpointKind::Webhook),
+        ));
+    }
+
+    match detect_spec_version(spec) {
+        SpecVersion::Swagger2 => {
+            for section in SWAGGER2_COMPONENT_SECTIONS {
+                if spec.contains_key(Value::String(section.to_string())) {
+                    counts.push(count(
+                        section,
+                        entries(output, &[section]),
+                        entries(spec, &[section]),
+                    ));
+                }
+            }
+        }
+        SpecVersion::OpenApi3 => {
+            let sections = spec
+                .get(Value::String("components".to_string()))
+                .and_then(|v| v.as_mapping())
+                .into_iter()
+                .flat_map(Mapping::keys)
+                .filter_map(|key| key.as_str());
+            for section in sections {
+                counts.push(count(
+                    section,
+                    entries(output, &["components", section]),
+                    entries(spec, &["components", section]),
+                ));
+            }
+        }
+    }
+
+    let tags = |root: &Mapping| {
+        root.get(Value::String("tags".to_string()))
+            .and_then(|v| v.as_sequence())
+            .map_or(0, Vec::len)
+    };
+    if spec.contains_key(Value::String("tags".to_string())) {
+        counts.push(count("tags", tags(output), tags(spec)));
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointer_segments_unescape_tilde_sequences() {
+        assert_eq!(
+            pointer_segments("#/paths/~1users~1{id}/get"),
+            vec!["paths", "/users/{id}", "get"]
+        );
+        assert_eq!(pointer_segments("/a~0b/~01"), vec!["a~b", "~1"]);
+        assert_eq!(pointer_segments("#"), Vec::<String>::new());
+        assert_eq!(pointer_segments("#/"), vec![""]);
+    }
+
+    #[test]
+    fn pointer_segments_decode_percent_escapes() {
+        assert_eq!(
+            pointer_segments("#/paths/~1users~1%7Bid%7D/get"),
+            vec!["paths", "/users/{id}", "get"]
+        );
+        assert_eq!(
+            pointer_segments("#/components/schemas/Pet%20Name"),
+            vec!["components", "schemas", "Pet Name"]
+        );
+    }
+
+    #[test]
+    fn escaped_segments_round_trip() {
+        for segment in ["/users/{id}", "a~b", "~1", "plain"] {
+            let pointer = format!("#/{}", escape_pointer_segment(segment));
+            assert_eq!(pointer_segments(&pointer), vec![segment]);
+        }
+    }
+
+    #[test]
+    fn resolve_local_ref_follows_mappings_and_sequences() {
+        let spec: Mapping = serde_yaml::from_str(
+            r#"
+paths:
+  /users/{id}:
+    get:
+      parameters:
+        - name: id
+        - name: verbose
+components:
+  schemas:
+    a~b: {type: string}
+"#,
+        )
+        .unwrap();
+        let name = |reference| {
+            resolve_local_ref(&spec, reference)
+                .and_then(|value| value.get("name"))
+                .and_then(Value::as_str)
+        };
+        assert_eq!(
+            name("#/paths/~1users~1{id}/get/parameters/1"),
+            Some("verbose")
+        );
+        assert_eq!(
+            name("#/paths/~1users~1%7Bid%7D/get/parameters/0"),
+            Some("id")
+        );
+        assert!(resolve_local_ref(&spec, "#/components/schemas/a~0b").is_some());
+        assert!(resolve_local_ref(&spec, "#/paths/~1users~1{id}/get/parameters/2").is_none());
+        assert!(resolve_local_ref(&spec, "#/paths/~1users/get").is_none());
+        assert!(resolve_local_ref(&spec, "other.yaml#/paths").is_none());
+    }
+
+    /// Snip the operations described like `GET /users` out of a YAML specification
+    fn snip(spec: &str, operations: &[&str]) -> Mapping {
+        let spec: Mapping = serde_yaml::from_str(spec).unwrap();
+        let (items, errors) = fetch_endpoints_from_spec(&spec);
+        assert!(errors.is_empty());
+        let selected: Vec<&Endpoint> = items
+            .iter()
+            .filter(|item| operations.contains(&item.key().describe().as_str()))
+            .collect();
+        assert_eq!(selected.len(), operations.len());
+        process_spec_for_output(&spec, &selected).unwrap()
+    }
+
+    /// The keys of the mapping at `pointer`, or nothing when it does not exist
+    fn keys(output: &Mapping, pointer: &str) -> Vec<String> {
+        resolve_pointer(&Value::Mapping(output.clone()), pointer)
+            .and_then(Value::as_mapping)
+            .map(|mapping| {
+                mapping
+                    .keys()
+                    .filter_map(|key| key.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn only_selected_operations_and_their_components_are_kept() {
+        let output = snip(
+            r#"
+openapi: 3.0.0
+info: {title: Users, version: '1'}
+paths:
+  /users/{id}:
+    summary: A single user
+    parameters:
+      - $ref: '#/components/parameters/Id'
+    get:
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema: {$ref: '#/components/schemas/User'}
+    delete:
+      responses:
+        '204': {$ref: '#/components/responses/Deleted'}
+  /health:
+    get:
+      responses:
+        '200': {description: ok}
+components:
+  parameters:
+    Id: {name: id, in: path, required: true, schema: {type: string}}
+  responses:
+    Deleted: {description: gone}
+  schemas:
+    User:
+      type: object
+      properties:
+        address: {$ref: '#/components/schemas/Address'}
+    Address: {type: object}
+    Unused: {type: object}
+"#,
+            &["GET /users/{id}"],
+        );
+        assert_eq!(keys(&output, "#/paths"), vec!["/users/{id}"]);
+        assert_eq!(
+            keys(&output, "#/paths/~1users~1{id}"),
+            vec!["summary", "parameters", "get"]
+        );
+        assert_eq!(keys(&output, "#/components"), vec!["parameters", "schemas"]);
+        assert_eq!(
+            keys(&output, "#/components/schemas"),
+            vec!["User", "Address"]
+        );
+    }
+
+    #[test]
+    fn swagger2_sections_are_pruned_like_components() {
+        let output = snip(
+            r#"
+swagger: '2.0'
+info: {title: Pets, version: '1'}
+paths:
+  /pets:
+    post:
+      security:
+        - apiKey: []
+      parameters:
+        - $ref: '#/parameters/Pet'
+      responses:
+        '200': {$ref: '#/responses/Created'}
+  /stores:
+    get:
+      security:
+        - oauth: []
+      responses:
+        '200':
+          description: ok
+          schema: {$ref: '#/definitions/Store'}
+parameters:
+  Pet:
+    name: pet
+    in: body
+    schema: {$ref: '#/definitions/Pet'}
+  Limit: {name: limit, in: query, type: integer}
+responses:
+  Created:
+    description: created
+    schema: {$ref: '#/definitions/Pet'}
+definitions:
+  Pet: {type: object}
+  Store: {type: object}
+securityDefinitions:
+  apiKey: {type: apiKey, name: key, in: header}
+  oauth: {type: oauth2, flow: implicit, authorizationUrl: 'https://example.com'}
+"#,
+            &["POST /pets"],
+        );
+        assert_eq!(keys(&output, "#/paths"), vec!["/pets"]);
+        assert_eq!(keys(&output, "#/parameters"), vec!["Pet"]);
+        assert_eq!(keys(&output, "#/responses"), vec!["Created"]);
+        assert_eq!(keys(&output, "#/definitions"), vec!["Pet"]);
+        assert_eq!(keys(&output, "#/securityDefinitions"), vec!["apiKey"]);
+        assert!(!output.contains_key("components"));
+    }
+
+    #[test]
+    fn unused_tags_and_tag_groups_are_dropped() {
+        let output = snip(
+            r#"
+openapi: 3.0.0
+info: {title: Shop, version: '1'}
+tags:
+  - name: orders
+    description: Order handling
+  - name: billing
+  - name: users
+x-tagGroups:
+  - name: Commerce
+    tags: [orders, billing]
+  - name: Accounts
+    tags: [users]
+paths:
+  /orders:
+    get:
+      tags: [orders]
+      responses:
+        '200': {description: ok}
+  /users:
+    get:
+      tags: [users]
+      responses:
+        '200': {description: ok}
+"#,
+            &["GET /orders"],
+        );
+        let expected: Value = serde_yaml::from_str(
+            r#"
+tags:
+  - name: orders
+    description: Order handling
+x-tagGroups:
+  - name: Commerce
+    tags: [orders]
+"#,
+        )
+        .unwrap();
+        assert_eq!(output.get("tags"), expected.get("tags"));
+        assert_eq!(output.get("x-tagGroups"), expected.get("x-tagGroups"));
+    }
+
+    #[test]
+    fn webhooks_are_snipped_with_their_components() {
+        let spec = r#"
+openapi: 3.1.0
+info: {title: Pets, version: '1'}
+paths:
+  /pets:
+    get:
+      responses:
+        '200': {description: ok}
+webhooks:
+  newPet:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema: {$ref: '#/components/schemas/Pet'}
+      responses:
+        '200': {description: ok}
+  petSold:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema: {$ref: '#/components/schemas/Sale'}
+      responses:
+        '200': {description: ok}
+components:
+  schemas:
+    Pet: {type: object}
+    Sale: {type: object}
+"#;
+        let output = snip(spec, &["POST webhook newPet"]);
+        assert!(keys(&output, "#/paths").is_empty());
+        assert_eq!(keys(&output, "#/webhooks"), vec!["newPet"]);
+        assert_eq!(keys(&output, "#/components/schemas"), vec!["Pet"]);
+
+        // The section disappears when no webhook is selected
+        let output = snip(spec, &["GET /pets"]);
+        assert!(!output.contains_key("webhooks"));
+        assert!(keys(&output, "#/components/schemas").is_empty());
+    }
+
+    const POLYMORPHIC: &str = r#"
+openapi: 3.0.0
+info: {title: Pets, version: '1'}
+paths:
+  /pets:
+    get:
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema: {$ref: '#/components/schemas/Pet'}
+  /dogs:
+    get:
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema: {$ref: '#/components/schemas/Dog'}
+  /owners:
+    get:
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema:
+                discriminator:
+                  propertyName: kind
+                  mapping:
+                    person: Person
+components:
+  schemas:
+    Pet:
+      type: object
+      discriminator: {propertyName: kind}
+    Dog:
+      allOf:
+        - $ref: '#/components/schemas/Pet'
+    Cat:
+      allOf:
+        - $ref: '#/components/schemas/Pet'
+        - properties:
+            size: {$ref: '#/components/schemas/Big'}
+    Big: {type: string}
+    Person: {type: object}
+"#;
+
+    #[test]
+    fn subtypes_of_a_referenced_base_are_kept() {
+        let output = snip(POLYMORPHIC, &["GET /pets"]);
+        assert_eq!(
+            keys(&output, "#/components/schemas"),
+            vec!["Pet", "Dog", "Cat", "Big"]
+        );
+    }
+
+    #[test]
+    fn siblings_of_a_referenced_subtype_are_dropped() {
+        let output = snip(POLYMORPHIC, &["GET /dogs"]);
+        assert_eq!(keys(&output, "#/components/schemas"), vec!["Pet", "Dog"]);
+    }
+
+    #[test]
+    fn discriminator_mapping_names_are_references() {
+        let output = snip(POLYMORPHIC, &["GET /owners"]);
+        assert_eq!(keys(&output, "#/components/schemas"), vec!["Person"]);
+    }
+
+    #[test]
+    fn links_to_dropped_operations_are_removed() {
+        let spec = r#"
+openapi: 3.0.0
+info: {title: Users, version: '1'}
+paths:
+  /users:
+    post:
+      responses:
+        '201':
+          description: created
+          links:
+            GetUser:
+              operationRef: '#/paths/~1users~1{id}/get'
+            DeleteUser:
+              $ref: '#/components/links/DeleteUser'
+  /users/{id}:
+    get:
+      operationId: getUser
+      responses:
+        '200': {description: ok}
+    delete:
+      responses:
+        '204': {description: gone}
+components:
+  links:
+    DeleteUser:
+      operationRef: '#/paths/~1users~1{id}/delete'
+"#;
+        let links = "#/paths/~1users/post/responses/201/links";
+        let output = snip(spec, &["POST /users", "GET /users/{id}"]);
+        assert_eq!(keys(&output, links), vec!["GetUser"]);
+
+        // Without any remaining link the key itself goes away
+        let output = snip(spec, &["POST /users"]);
+        assert_eq!(
+            keys(&output, "#/paths/~1users/post/responses/201"),
+            vec!["description"]
+        );
+    }
+}
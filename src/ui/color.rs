@@ -72,6 +72,20 @@ pub fn gradient_color(
     }
 }
 
+// Emphasise the characters a search matched, falling back to underlining without color support
+pub fn match_highlight_style(color_level: Option<ColorLevel>, color_mode: Mode) -> Style {
+    match (color_level, color_mode) {
+        (None, _) => Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        // Yellow is hard to read on a light background
+        (Some(_), Mode::Light) => Style::default()
+            .fg(Color::Magenta)
+            .add_modifier(Modifier::BOLD),
+        (Some(_), _) => Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    }
+}
+
 // Calculate dimmed foreground color based on color mode
 pub fn calculate_dimmed_color(foreground: (u8, u8, u8), color_mode: Mode) -> (u8, u8, u8) {
     match color_mode {
@@ -144,14 +158,24 @@ pub fn rgb_to_indexed(r: u8, g: u8, b: u8) -> u8 {
 }
 
 // Set color preferences based on terminal background
-pub fn set_color_preferences(color_mode: &mut Mode, default_foreground_color: &mut (u8, u8, u8)) {
-    match terminal_light::luma() {
-        Ok(luma) if luma > 0.85 => {
+// The query is written to stdout, so callers skip it when stdout carries the output
+pub fn set_color_preferences(
+    color_mode: &mut Mode,
+    default_foreground_color: &mut (u8, u8, u8),
+    query_terminal: bool,
+) {
+    let luma = if query_terminal {
+        terminal_light::luma().ok()
+    } else {
+        None
+    };
+    match luma {
+        Some(luma) if luma > 0.85 => {
             // Light mode: use a dark gray (#333333)
             *default_foreground_color = hex_to_rgb(0x333333);
             *color_mode = Mode::Light;
         }
-        Ok(luma) if luma < 0.2 => {
+        Some(luma) if luma < 0.2 => {
             // Dark mode: use a light gray (#C0C0C0)
             *default_foreground_color = hex_to_rgb(0xC0C0C0);
             *color_mode = Mode::Dark;
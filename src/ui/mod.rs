@@ -1,15 +1,17 @@
 pub mod color;
 pub mod widget;
 
-use crate::spec_processor::{Method, Status};
-use crate::ui::color::gradient_color;
+use crate::file;
+use crate::spec_processor::{Endpoint, EndpointKind, Method, Status};
+use crate::ui::color::{gradient_color, match_highlight_style};
 use crate::ui::widget::Shortcuts;
-use ratatui::layout::{Alignment, Constraint, Rect};
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
 use ratatui::prelude::Stylize;
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{
-    Block, BorderType, Borders, Padding, Paragraph, Row, Scrollbar, ScrollbarState, Table,
+    Block, BorderType, Borders, Clear, Padding, Paragraph, Row, Scrollbar, ScrollbarState, Table,
+    Wrap,
 };
 use ratatui::{symbols, Frame};
 use widget::Shortcut;
@@ -36,7 +38,10 @@ pub fn render_table(model: &mut crate::AppModel, area: Rect, frame: &mut Frame)
                 Block::default()
                     .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
                     .border_type(BorderType::Rounded)
-                    .title(format!(" 0 endpoints for {} ", model.infile))
+                    .title(format!(
+                        " 0 operations for {} ",
+                        file::without_credentials(&model.infile)
+                    ))
                     .title_alignment(Alignment::Center),
             )
             .alignment(Alignment::Center);
@@ -45,36 +50,43 @@ pub fn render_table(model: &mut crate::AppModel, area: Rect, frame: &mut Frame)
         return;
     }
 
-    let header = Row::new(vec!["    Summary", "Path", "Methods"])
+    let header = Row::new(vec!["    Summary", "Path", "Method"])
         .style(Style::default().add_modifier(Modifier::BOLD))
         .height(1);
 
     // Get the currently selected index for calculating distance
     let selected_idx = model.table_state.selected().unwrap_or(0);
 
-    let rows = model.table_items.iter().enumerate().map(|(idx, data)| {
-        // Use a reference to the description to avoid cloning
-        let description = if data.description.is_empty() {
-            data.methods
-                .iter()
-                .map(|method| method.description.as_str())
-                .collect::<Vec<&str>>()
-                .join("/")
+    // Show which characters the search matched
+    let highlight_style = match_highlight_style(model.color_support, model.color_mode);
+    let highlighted = |text: &str| {
+        let indices = if model.search_state.active {
+            model.search_state.query.highlights(text, &model.matcher)
         } else {
-            data.description.to_string()
+            Vec::new()
         };
+        highlight_matches(text, &indices, highlight_style)
+    };
+
+    // Rows of the visual range are reversed like the current row
+    let visual_range = model.visual_range();
 
-        let description_selection = match data.status {
-            Status::Unselected => format!("    {}", description),
-            Status::Selected => format!(" ✂️ {}", description),
+    let rows = model.table_items.iter().enumerate().map(|(idx, data)| {
+        let selection_marker = match data.status {
+            Status::Unselected => "    ",
+            Status::Selected => " ✂️ ",
         };
+        let mut description_selection = vec![Span::from(selection_marker)];
+        description_selection.extend(highlighted(data.summary()));
 
-        // Calculate distance from selected row to apply gradient
-        let distance = if idx > selected_idx {
-            idx - selected_idx
-        } else {
-            selected_idx - idx
+        let mut path = match data.kind {
+            EndpointKind::Path => Vec::new(),
+            EndpointKind::Webhook => vec![Span::from("⚡ ")],
         };
+        path.extend(highlighted(&data.path));
+
+        // Calculate distance from selected row to apply gradient
+        let distance = idx.abs_diff(selected_idx);
 
         // Determine if this row should be selected (green)
         let is_selected_item = data.status == Status::Selected;
@@ -88,16 +100,15 @@ pub fn render_table(model: &mut crate::AppModel, area: Rect, frame: &mut Frame)
             model.default_foreground_color,
             model.color_mode,
         );
+        let row_style = match &visual_range {
+            Some(range) if range.contains(&idx) => row_style.add_modifier(Modifier::REVERSED),
+            _ => row_style,
+        };
 
-        // Use references for path and methods to avoid cloning
         Row::new(vec![
-            description_selection,
-            data.path.to_string(),
-            data.methods
-                .iter()
-                .map(|method| method.method.to_uppercase())
-                .collect::<Vec<String>>()
-                .join(" "),
+            Line::from(description_selection),
+            Line::from(path),
+            Line::from(data.method.method.to_uppercase()),
         ])
         .height(1)
         .style(row_style)
@@ -105,7 +116,11 @@ pub fn render_table(model: &mut crate::AppModel, area: Rect, frame: &mut Frame)
 
     let table = Table::new(
         rows,
-        [Constraint::Min(20), Constraint::Min(20), Constraint::Min(1)],
+        [
+            Constraint::Min(20),
+            Constraint::Min(20),
+            Constraint::Length(7),
+        ],
     )
     .header(header)
     .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED | Modifier::ITALIC))
@@ -114,9 +129,9 @@ pub fn render_table(model: &mut crate::AppModel, area: Rect, frame: &mut Frame)
             .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
             .border_type(BorderType::Rounded)
             .title(format!(
-                " {} endpoints for {} ",
+                " {} operations for {} ",
                 model.table_items.len(),
-                model.infile
+                file::without_credentials(&model.infile)
             ))
             .title_alignment(Alignment::Center)
             .style(model.default_style),
@@ -172,23 +187,18 @@ pub fn render_detail(model: &crate::AppModel, area: Rect, frame: &mut Frame) {
     }
 
     let selected_item = &model.table_items[selected_idx];
-    let mut description = selected_item.description.clone();
-    if description.is_empty() {
-        description = selected_item
-            .methods
-            .iter()
-            .map(|method| method.description.as_str())
-            .collect::<Vec<&str>>()
-            .join("/");
-    }
+    let description = if selected_item.description.is_empty() {
+        selected_item.summary().to_string()
+    } else {
+        selected_item.description.clone()
+    };
 
-    let mut detail_lines: Vec<Line> = Vec::new();
-    detail_lines.push(Line::from(description));
-    detail_lines.push(Line::from("".to_string()));
-    detail_lines.push(Line::from(selected_item.path.clone()).style(Style::default()));
-    for method in selected_item.methods.iter() {
-        detail_lines.push(styled_method_with_description(method, 6));
-    }
+    let mut detail_lines: Vec<Line> = vec![
+        Line::from(description),
+        Line::from("".to_string()),
+        Line::from(display_path(selected_item)).style(Style::default()),
+        styled_method_with_description(&selected_item.method, 6),
+    ];
 
     if !selected_item.refs.is_empty() || !selected_item.parameters.is_empty() {
         detail_lines.push(Line::from("".to_string()));
@@ -219,13 +229,24 @@ pub fn render_detail(model: &crate::AppModel, area: Rect, frame: &mut Frame) {
         ..symbols::border::PLAIN
     };
 
-    let shortcuts = Shortcuts::new(vec![
-        Shortcut::Pair("space", "✂️snip"),
-        Shortcut::Pair("w", "write and quit"),
-        Shortcut::Pair("/", "search"),
-        Shortcut::Trio("▼", "move", "▲"),
-        Shortcut::Pair("q", "quit"),
-    ])
+    let shortcuts = Shortcuts::new(if model.visual_anchor.is_some() {
+        vec![
+            Shortcut::Pair("space", "✂️snip range"),
+            Shortcut::Trio("▼", "extend", "▲"),
+            Shortcut::Pair("Esc", "cancel"),
+        ]
+    } else {
+        vec![
+            Shortcut::Pair("space", "✂️snip"),
+            Shortcut::Pair("v", "range"),
+            Shortcut::Pair("a", "snip all"),
+            Shortcut::Pair("w", "preview and write"),
+            Shortcut::Pair("s", "save"),
+            Shortcut::Pair("/", "search"),
+            Shortcut::Trio("▼", "move", "▲"),
+            Shortcut::Pair("q", "quit"),
+        ]
+    })
     .with_alignment(Alignment::Right)
     .with_label_style(model.default_style.add_modifier(Modifier::BOLD));
 
@@ -239,16 +260,36 @@ pub fn render_detail(model: &crate::AppModel, area: Rect, frame: &mut Frame) {
         Block::default()
             .borders(Borders::ALL)
             .border_set(collapsed_top_border_set)
-            .title(if selected_item_count > 0 {
+            .title({
+                let mut title = Vec::new();
+                if selected_item_count > 0 {
+                    title.extend([
+                        " ".into(),
+                        selected_item_count.to_string().bold().green(),
+                        " operations selected ".into(),
+                    ]);
+                }
+                // Changes since the last write
+                if model.dirty {
+                    title.push(" unsaved ".italic().yellow());
+                }
+                Line::from(title)
+            })
+            .title_alignment(Alignment::Right)
+            .title(if model.warnings.is_empty() {
+                Line::from("")
+            } else {
                 Line::from(vec![
                     " ".into(),
-                    selected_item_count.to_string().bold().green(),
-                    " endpoints selected ".into(),
+                    model.warnings.len().to_string().bold().yellow(),
+                    " warnings, press ! to show ".into(),
                 ])
-            } else {
-                Line::from("")
+                .left_aligned()
+            })
+            .title(match &model.status_message {
+                Some(message) => Line::from(format!(" {} ", message)).left_aligned(),
+                None => Line::from(""),
             })
-            .title_alignment(Alignment::Right)
             .title_bottom(shortcuts.as_line())
             .padding(Padding::new(1, 1, 0, 0))
             .style(model.default_style),
@@ -256,6 +297,209 @@ pub fn render_detail(model: &crate::AppModel, area: Rect, frame: &mut Frame) {
     frame.render_widget(detail, area);
 }
 
+pub fn render_warnings(model: &crate::AppModel, area: Rect, frame: &mut Frame) {
+    let collapsed_top_border_set = symbols::border::Set {
+        top_left: symbols::line::NORMAL.vertical_right,
+        top_right: symbols::line::NORMAL.vertical_left,
+        bottom_right: symbols::line::ROUNDED_BOTTOM_RIGHT,
+        bottom_left: symbols::line::ROUNDED_BOTTOM_LEFT,
+        ..symbols::border::PLAIN
+    };
+
+    let shortcuts = Shortcuts::new(vec![
+        Shortcut::Pair("!", "hide warnings"),
+        Shortcut::Pair("q", "quit"),
+    ])
+    .with_alignment(Alignment::Right)
+    .with_label_style(model.default_style.add_modifier(Modifier::BOLD));
+
+    let warning_lines: Vec<Line> = model
+        .warnings
+        .iter()
+        .map(|warning| Line::from(vec!["⚠ ".yellow(), Span::from(warning.as_str())]))
+        .collect();
+
+    let warnings = Paragraph::new(Text::from(warning_lines))
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(collapsed_top_border_set)
+                .title(Line::from(vec![
+                    " ".into(),
+                    model.warnings.len().to_string().bold().yellow(),
+                    " warnings, also listed on exit ".into(),
+                ]))
+                .title_bottom(shortcuts.as_line())
+                .padding(Padding::new(1, 1, 0, 0))
+                .style(model.default_style),
+        );
+    frame.render_widget(warnings, area);
+}
+
+pub fn render_preview(model: &mut crate::AppModel, area: Rect, frame: &mut Frame) {
+    let Some(preview) = &mut model.preview else {
+        return;
+    };
+    let [counts_area, output_area] =
+        Layout::horizontal([Constraint::Length(40), Constraint::Min(20)]).areas(area);
+
+    // Kept and dropped entries per section
+    let header = Row::new(vec!["Section", "Kept", "Dropped"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = preview.counts.iter().map(|count| {
+        let dropped = count.total - count.kept.min(count.total);
+        Row::new(vec![
+            Span::from(count.name.clone()),
+            count.kept.to_string().green(),
+            if dropped > 0 {
+                dropped.to_string().red()
+            } else {
+                dropped.to_string().dim()
+            },
+        ])
+    });
+    let counts = Table::new(
+        rows,
+        [
+            Constraint::Min(20),
+            Constraint::Length(6),
+            Constraint::Length(7),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Summary ")
+            .padding(Padding::new(1, 1, 0, 0))
+            .style(model.default_style),
+    );
+    frame.render_widget(counts, counts_area);
+
+    // Only the visible lines are handed to the widget, large specifications have many
+    preview.page_height = output_area.height.saturating_sub(2);
+    let visible_lines: Vec<Line> = preview
+        .lines
+        .iter()
+        .skip(preview.scroll)
+        .take(preview.page_height as usize)
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+
+    let shortcuts = Shortcuts::new(vec![
+        Shortcut::Pair("Enter", "write and quit"),
+        Shortcut::Pair("o", "output path"),
+        Shortcut::Trio("▼", "scroll", "▲"),
+        Shortcut::Pair("Esc", "back"),
+    ])
+    .with_alignment(Alignment::Right)
+    .with_label_style(model.default_style.add_modifier(Modifier::BOLD));
+
+    let output = Paragraph::new(Text::from(visible_lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(format!(" Preview of {} ", model.outfile))
+            .title_alignment(Alignment::Center)
+            .title_bottom(shortcuts.as_line())
+            .padding(Padding::new(1, 1, 0, 0))
+            .style(model.default_style),
+    );
+    frame.render_widget(output, output_area);
+
+    let mut scrollbar_state = ScrollbarState::new(
+        preview
+            .lines
+            .len()
+            .saturating_sub(preview.page_height as usize),
+    )
+    .position(preview.scroll)
+    .viewport_content_length(preview.page_height as usize);
+    let scrollbar = Scrollbar::default()
+        .orientation(ratatui::widgets::ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .track_symbol(None)
+        .thumb_symbol("█");
+    frame.render_stateful_widget(
+        scrollbar,
+        output_area.inner(ratatui::layout::Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut scrollbar_state,
+    );
+}
+
+pub fn render_output_path_prompt(model: &crate::AppModel, area: Rect, frame: &mut Frame) {
+    let Some(input) = &model.path_input else {
+        return;
+    };
+    let popup = centered_rect(area, 70, 3);
+    let shortcuts = Shortcuts::new(vec![
+        Shortcut::Pair("Enter", "accept"),
+        Shortcut::Pair("Esc", "cancel"),
+    ])
+    .with_alignment(Alignment::Right)
+    .with_label_style(model.default_style.add_modifier(Modifier::BOLD));
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" Output path ")
+        .title_bottom(shortcuts.as_line())
+        .padding(Padding::new(1, 1, 0, 0))
+        .style(model.default_style);
+    let inner_area = block.inner(popup);
+    frame.render_widget(Clear, popup);
+    frame.render_widget(block, popup);
+    frame.render_widget(input, inner_area);
+}
+
+pub fn render_quit_confirmation(model: &crate::AppModel, area: Rect, frame: &mut Frame) {
+    let popup = centered_rect(area, 60, 5);
+    let shortcuts = Shortcuts::new(vec![
+        Shortcut::Pair("y", "write"),
+        Shortcut::Pair("n", "discard"),
+        Shortcut::Pair("Esc", "cancel"),
+    ])
+    .with_alignment(Alignment::Center)
+    .with_label_style(model.default_style.add_modifier(Modifier::BOLD));
+    let question = Paragraph::new(Text::from(vec![
+        Line::from("The selection has changed since it was last written."),
+        Line::from(vec![
+            "Write it to ".into(),
+            model.outfile.as_str().bold(),
+            " before quitting?".into(),
+        ]),
+    ]))
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Unsaved changes ")
+            .title_alignment(Alignment::Center)
+            .title_bottom(shortcuts.as_line())
+            .style(model.default_style),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(question, popup);
+}
+
+// A rectangle of the given size in the middle of `area`, shrunk to fit
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
 pub fn render_search(model: &mut crate::AppModel, area: Rect, frame: &mut Frame) {
     let collapsed_top_border_set = symbols::border::Set {
         top_left: symbols::line::NORMAL.vertical_right,
@@ -265,6 +509,13 @@ pub fn render_search(model: &mut crate::AppModel, area: Rect, frame: &mut Frame)
 
     let shortcuts = Shortcuts::new(vec![
         Shortcut::Pair("🔍", "search"),
+        if model.visual_anchor.is_some() {
+            Shortcut::Pair("Tab", "snip range")
+        } else {
+            Shortcut::Pair("Tab", "snip")
+        },
+        Shortcut::Pair("Ctrl+V", "range"),
+        Shortcut::Pair("Ctrl+A", "snip all"),
         Shortcut::Pair("Esc", "exit search"),
         Shortcut::Pair("Ctrl+U", "clear search"),
     ])
@@ -290,7 +541,43 @@ pub fn render_search(model: &mut crate::AppModel, area: Rect, frame: &mut Frame)
     frame.render_widget(&model.search_state.text_input, inner_area);
 }
 
-fn styled_method_with_description(method: &Method, padding: usize) -> Line {
+// Webhooks are named rather than addressed by path, so mark them as such
+fn display_path(item: &Endpoint) -> String {
+    match item.kind {
+        EndpointKind::Path => item.path.clone(),
+        EndpointKind::Webhook => format!("⚡ {}", item.path),
+    }
+}
+
+/// Split text into spans, styling the characters at the given positions
+fn highlight_matches(text: &str, indices: &[usize], style: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (idx, c) in text.chars().enumerate() {
+        let matched = indices.binary_search(&idx).is_ok();
+        if matched != run_matched && !run.is_empty() {
+            let run = std::mem::take(&mut run);
+            spans.push(if run_matched {
+                Span::styled(run, style)
+            } else {
+                Span::from(run)
+            });
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(if run_matched {
+            Span::styled(run, style)
+        } else {
+            Span::from(run)
+        });
+    }
+    spans
+}
+
+fn styled_method_with_description(method: &Method, padding: usize) -> Line<'_> {
     Line::from(vec![
         colored_method(&method.method, padding).add_modifier(Modifier::BOLD),
         Span::from(" "),
@@ -298,7 +585,7 @@ fn styled_method_with_description(method: &Method, padding: usize) -> Line {
     ])
 }
 
-fn colored_method(method: &str, padding: usize) -> Span {
+fn colored_method(method: &str, padding: usize) -> Span<'_> {
     let method_str = method.to_uppercase();
     let the_method: Span = if padding > 0 {
         Span::from(format!("{:<padding$}", method_str.clone()))
@@ -48,7 +48,7 @@ impl Shortcuts {
     }
 
     /// Get the line representation of all shortcuts
-    pub fn as_line(&self) -> Line {
+    pub fn as_line(&self) -> Line<'_> {
         if self.shortcuts.is_empty() {
             return Line::default().alignment(self.alignment);
         }